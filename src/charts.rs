@@ -0,0 +1,307 @@
+//! Charts drawn with Unicode block characters: a daily bar chart stacked by top-level task, and a
+//! calendar heatmap of a year. Glyphs tell the series and levels apart even without colours.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use dialoguer::console::Style;
+use std::collections::HashMap;
+
+use crate::models::ListItem;
+use crate::report::{group_by_day, total_effort};
+use crate::utils::nice::Nice;
+
+/// One glyph per series of the daily chart; beyond these, the smaller prefixes share the last.
+const GLYPHS: [char; 4] = ['█', '▓', '▒', '░'];
+
+/// Heatmap levels and the hours from which they start, from nothing tracked to a full working day
+/// or more.
+const LEVELS: [(char, i64); 5] = [('·', 0), ('░', 0), ('▒', 2), ('▓', 4), ('█', 8)];
+
+const DAY_LABEL_WIDTH: usize = 14;
+
+fn series_style(i: usize) -> Style {
+    match i {
+        0 => Style::new().blue(),
+        1 => Style::new().magenta(),
+        2 => Style::new().cyan(),
+        _ => Style::new().yellow(),
+    }
+}
+
+/// Effort per day in the given period, split by top-level task. Days without entries are kept,
+/// so that gaps show.
+fn per_day(
+    data: &[ListItem],
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Vec<(NaiveDate, HashMap<&str, Duration>)> {
+    let mut days: HashMap<NaiveDate, HashMap<&str, Duration>> = HashMap::new();
+
+    for (key, items) in group_by_day(data) {
+        let day = days.entry(key.naive_local()).or_default();
+        for x in items {
+            let prefix = x.id.split('/').next().unwrap_or_default();
+            let e = day.entry(prefix).or_insert_with(Duration::zero);
+            *e += total_effort(std::slice::from_ref(x));
+        }
+    }
+
+    first
+        .iter_days()
+        .take_while(|d| *d <= last)
+        .map(|d| (d, days.remove(&d).unwrap_or_default()))
+        .collect()
+}
+
+/// Horizontal bars of the effort per day, stacked by top-level task, followed by a legend. Totals
+/// over `target` are marked.
+pub fn daily<F>(
+    data: &[ListItem],
+    first: NaiveDate,
+    last: NaiveDate,
+    target: F,
+    width: usize,
+) -> String
+where
+    F: Fn(NaiveDate) -> Duration,
+{
+    let days = per_day(data, first, last);
+
+    // The biggest prefixes get their own glyph, the rest are lumped together
+    let mut totals: HashMap<&str, Duration> = HashMap::new();
+    for (_, d) in &days {
+        for (k, v) in d {
+            let e = totals.entry(k).or_insert_with(Duration::zero);
+            *e += *v;
+        }
+    }
+    let mut prefixes: Vec<(&str, Duration)> = totals.into_iter().collect();
+    prefixes.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(y.0)));
+
+    let lumped = prefixes.len() > GLYPHS.len();
+    let named = match lumped {
+        true => GLYPHS.len() - 1,
+        false => prefixes.len(),
+    };
+    let series = |prefix: &str| {
+        prefixes[..named]
+            .iter()
+            .position(|(p, _)| *p == prefix)
+            .unwrap_or(named)
+    };
+
+    let day_total = |d: &HashMap<&str, Duration>| d.values().fold(Duration::zero(), |a, x| a + *x);
+    let max = days
+        .iter()
+        .map(|(_, d)| day_total(d).num_seconds())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let total_width = days
+        .iter()
+        .map(|(_, d)| day_total(d).nice().chars().count())
+        .max()
+        .unwrap_or(0);
+    let bar_width = width
+        .saturating_sub(DAY_LABEL_WIDTH + total_width + 4)
+        .max(10);
+
+    let mut lines = Vec::new();
+    for (day, d) in &days {
+        let mut efforts = vec![Duration::zero(); named + lumped as usize];
+        for (k, v) in d {
+            efforts[series(k)] = efforts[series(k)] + *v;
+        }
+
+        // Ends are rounded from the running total, so that rounding errors do not add up
+        let mut bar = String::new();
+        let mut seconds = 0;
+        let mut drawn = 0;
+        for (i, e) in efforts.iter().enumerate() {
+            seconds += e.num_seconds();
+            let end = (seconds as usize * bar_width + max as usize / 2) / max as usize;
+            let glyph = GLYPHS[i.min(GLYPHS.len() - 1)].to_string();
+            bar.push_str(
+                &series_style(i)
+                    .apply_to(glyph.repeat(end - drawn))
+                    .to_string(),
+            );
+            drawn = end;
+        }
+        bar.push_str(&" ".repeat(bar_width - drawn));
+
+        let total = day_total(d);
+        let over = total > target(*day);
+        let mark = match over {
+            true => Style::new().red().bold().apply_to("*").to_string(),
+            false => " ".to_string(),
+        };
+        lines.push(format!(
+            "{} {} {:>w$}{}",
+            day.format("%a %F"),
+            bar,
+            total.nice(),
+            mark,
+            w = total_width
+        ));
+    }
+
+    let mut legend: Vec<String> = prefixes[..named]
+        .iter()
+        .enumerate()
+        .map(|(i, (p, _))| format!("{} {}", series_style(i).apply_to(GLYPHS[i]), p))
+        .collect();
+    if lumped {
+        legend.push(format!(
+            "{} other",
+            series_style(named).apply_to(GLYPHS[named])
+        ));
+    }
+    legend.push("* over the working schedule".to_string());
+    lines.push(String::new());
+    lines.push(legend.join("  "));
+
+    lines.join("\n") + "\n"
+}
+
+fn level(effort: Duration) -> usize {
+    match effort.num_seconds() {
+        0 => 0,
+        s => LEVELS.iter().rposition(|(_, h)| s >= h * 3600).unwrap_or(1),
+    }
+}
+
+fn level_style(level: usize) -> Style {
+    match level {
+        0 => Style::new().dim(),
+        1 | 2 => Style::new().green(),
+        _ => Style::new().green().bold(),
+    }
+}
+
+/// A grid of the effort per day, one column per week, like the contribution graph of GitHub.
+pub fn heatmap(data: &[ListItem], first: NaiveDate, last: NaiveDate) -> String {
+    let days = per_day(data, first, last);
+    let effort: HashMap<NaiveDate, Duration> = days
+        .iter()
+        .map(|(d, x)| (*d, x.values().fold(Duration::zero(), |a, y| a + *y)))
+        .collect();
+
+    // Columns start on Monday
+    let start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    let weeks = ((last - start).num_days() / 7 + 1) as usize;
+    let column = |d: NaiveDate| ((d - start).num_days() / 7) as usize;
+
+    // Month names over the first week of each month, where they fit
+    let mut months = vec![' '; weeks + 3];
+    let mut free = 0;
+    for (d, _) in &days {
+        if d.day() == 1 || *d == first {
+            let c = column(*d);
+            if c >= free {
+                for (i, ch) in d.format("%b").to_string().chars().enumerate() {
+                    months[c + i] = ch;
+                }
+                free = c + 4;
+            }
+        }
+    }
+
+    let mut lines = vec![format!(
+        "    {}",
+        months.iter().collect::<String>().trim_end()
+    )];
+    for row in 0..7 {
+        let weekday = start + Duration::days(row);
+        let mut line = format!("{} ", weekday.format("%a"));
+        for c in 0..weeks {
+            let d = start + Duration::days(c as i64 * 7 + row);
+            match effort.get(&d) {
+                Some(e) => {
+                    let l = level(*e);
+                    line.push_str(&level_style(l).apply_to(LEVELS[l].0).to_string());
+                }
+                None => line.push(' '),
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    let legend: Vec<String> = LEVELS
+        .iter()
+        .enumerate()
+        .map(|(i, (g, h))| {
+            let label = match (i, LEVELS.get(i + 1)) {
+                (0, _) => "none".to_string(),
+                (_, Some((_, next))) => format!("<{}h", next),
+                (_, None) => format!("{}h+", h),
+            };
+            format!("{} {}", level_style(i).apply_to(g), label)
+        })
+        .collect();
+    lines.push(String::new());
+    lines.push(legend.join("  "));
+
+    let total = effort.values().fold(Duration::zero(), |a, x| a + *x);
+    let worked = effort.values().filter(|x| x.num_seconds() > 0).count();
+    let busiest = days
+        .iter()
+        .map(|(d, _)| (*d, effort[d]))
+        .max_by(|x, y| x.1.cmp(&y.1).then(y.0.cmp(&x.0)));
+    let mut summary = format!("Tracked {} on {} days", total.nice(), worked);
+    if let Some((d, e)) = busiest.filter(|(_, e)| e.num_seconds() > 0) {
+        summary.push_str(&format!("; busiest: {} ({})", d.format("%a %F"), e.nice()));
+    }
+    lines.push(summary);
+
+    lines.join("\n") + "\n"
+}
+
+/// The Monday 52 weeks before the week of `day`, so that a year fits in the heatmap.
+pub fn year_before(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(i64::from(day.weekday().num_days_from_monday())) - Duration::weeks(52)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{daily, heatmap, year_before};
+    use crate::models::list_item;
+    use chrono::{Duration, NaiveDate};
+
+    #[test]
+    fn test_charts() {
+        // Most recent first, as listed
+        let data = vec![
+            list_item("home/chores", "2021-03-03-18:00", Some("2021-03-03-19:00")),
+            list_item("acme/web", "2021-03-03-13:00", Some("2021-03-03-17:00")),
+            list_item("acme/api", "2021-03-03-09:00", Some("2021-03-03-12:00")),
+            list_item("acme/api", "2021-03-01-09:00", Some("2021-03-01-13:00")),
+        ];
+        let first = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(2021, 3, 3).unwrap();
+
+        let chart = daily(&data, first, last, |_| Duration::hours(6), 38);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines[0], "Mon 2021-03-01 █████████          4h ");
+        assert_eq!(lines[1], "Tue 2021-03-02                    0s ");
+        assert_eq!(lines[2], "Wed 2021-03-03 ████████████████▓▓ 8h*");
+        assert_eq!(lines[4], "█ acme  ▓ home  * over the working schedule");
+
+        let chart = heatmap(&data, first, last);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines[0], "    Mar");
+        assert_eq!(lines[1], "Mon ▓");
+        assert_eq!(lines[2], "Tue ·");
+        assert_eq!(lines[3], "Wed █");
+        assert_eq!(lines[4], "Thu");
+        assert_eq!(
+            lines[10],
+            "Tracked 12h on 2 days; busiest: Wed 2021-03-03 (8h)"
+        );
+
+        assert_eq!(
+            year_before(NaiveDate::from_ymd_opt(2021, 3, 3).unwrap()),
+            NaiveDate::from_ymd_opt(2020, 3, 2).unwrap()
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use clap::{App, AppSettings, Arg, ArgMatches};
+use clap::{App, AppSettings, Arg, ArgMatches, ArgSettings, ValueHint};
 
-use crate::models::Task;
+use dit::models::Task;
 
 fn fetch_arg<'a>() -> Arg<'a> {
     Arg::new("fetch")
@@ -17,11 +17,18 @@ fn task_param<'a>() -> Arg<'a> {
         .validator(Task::validate_key)
 }
 
+fn picked_task_param<'a>() -> Arg<'a> {
+    task_param()
+        .about("The target task for the command. If absent, you'll be prompted to pick one of the recent tasks.")
+        .required(false)
+}
+
 fn new_arg<'a>() -> Arg<'a> {
     Arg::new("new")
         .about("Also create the task.")
         .long("new")
         .short('n')
+        .requires("task")
 }
 
 fn title_arg<'a>() -> Arg<'a> {
@@ -40,6 +47,13 @@ fn at_arg<'a>() -> Arg<'a> {
         .allow_hyphen_values(true)
 }
 
+fn force_arg<'a>() -> Arg<'a> {
+    Arg::new("force")
+        .about("Halts even if the entry would be longer than the maximum entry length.")
+        .long("force")
+        .short('f')
+}
+
 fn properties_arg<'a>() -> Arg<'a> {
     Arg::new("properties")
         .about("Selects the properties to be printed for each task.")
@@ -49,11 +63,72 @@ fn properties_arg<'a>() -> Arg<'a> {
         .use_delimiter(true)
 }
 
+fn estimate_arg<'a>() -> Arg<'a> {
+    Arg::new("estimate")
+        .about("Expected effort for the task, e.g. '8h' or '2d4h'.")
+        .value_name("DURATION")
+        .long("estimate")
+        .short('e')
+}
+
+fn after_arg<'a>() -> Arg<'a> {
+    Arg::new("after")
+        .about("Consider only entries from after this date.")
+        .value_name("DATETIME")
+        .long("after")
+        .short('a')
+}
+
+fn before_arg<'a>() -> Arg<'a> {
+    Arg::new("before")
+        .about("Consider only entries from before this date.")
+        .value_name("DATETIME")
+        .long("before")
+        .short('b')
+}
+
+fn include_archived_arg<'a>() -> Arg<'a> {
+    Arg::new("include-archived")
+        .about("Also considers archived tasks.")
+        .long("include-archived")
+}
+
+fn table_format_arg<'a>() -> Arg<'a> {
+    Arg::new("format")
+        .about("Specifies which format to use when printing: a table for the terminal, a GitHub-flavoured Markdown table, or a self-contained HTML page.")
+        .value_name("FORMAT")
+        .long("format")
+        .short('f')
+        .possible_values(&["table", "markdown", "html"])
+        .default_value("table")
+}
+
+fn template_arg<'a>() -> Arg<'a> {
+    Arg::new("template")
+        .about("Prints each item with a template instead of a table, e.g. '{start:%H:%M}-{end:%H:%M} {id} {note}', or the name of one under '[templates]' in the configuration. Fields: id, title, start, end, effort, total-effort, estimate, remaining, progress and note. Timestamps take a strftime format and durations a style: hm, h:mm, min or h. Filters follow the field: trunc:N, pad:N, lpad:N and or:TEXT, e.g. {title|trunc:30|pad:30}.")
+        .value_name("TEMPLATE")
+        .long("template")
+        .short('t')
+}
+
+fn csv_mapping_arg<'a>() -> Arg<'a> {
+    Arg::new("mapping")
+        .about("Which column mapping to use: 'toggl', 'clockify', or one defined under '[csv.mappings]' in the configuration.")
+        .value_name("MAPPING")
+        .long("mapping")
+        .short('m')
+        .default_value("toggl")
+}
+
 fn new_app<'a>(name: &str) -> App<'a> {
     App::new(name).setting(AppSettings::UnifiedHelpMessage)
 }
 
 pub fn parse() -> ArgMatches {
+    build().get_matches()
+}
+
+pub fn build() -> App<'static> {
     new_app(env!("CARGO_PKG_NAME"))
         .setting(AppSettings::SubcommandRequired)
         .setting(AppSettings::DeriveDisplayOrder)
@@ -66,9 +141,16 @@ pub fn parse() -> ArgMatches {
             .long("directory")
             .short('d')
             .value_name("DIRECTORY")
+            .value_hint(ValueHint::DirPath)
             .takes_value(true)
             .global(true)
         )
+        .arg(
+            Arg::new("no-pager")
+            .about("Prints listings straight to the terminal instead of through $PAGER. Colours can be turned off by setting NO_COLOR.")
+            .long("no-pager")
+            .global(true)
+        )
         .arg(
             Arg::new("verbose")
             .about("Prints detailed information of what is being done.")
@@ -106,13 +188,14 @@ pub fn parse() -> ArgMatches {
                 .value_name("TITLE")
                 .required(false)
             )
+            .arg(estimate_arg())
             .arg(fetch_arg())
         )
         .subcommand(
             new_app("work-on")
             .visible_alias("w")
             .about("Starts clocking on the specified task.")
-            .arg(task_param())
+            .arg(picked_task_param())
             .arg(at_arg())
             .arg(new_arg())
             .arg(fetch_arg())
@@ -123,6 +206,19 @@ pub fn parse() -> ArgMatches {
             .visible_alias("h")
             .about("Stops clocking on the currently active task.")
             .arg(at_arg())
+            .arg(force_arg())
+        )
+        .subcommand(
+            new_app("fix-open")
+            .about("Ends an entry left running for too long, at a time picked from suggestions based on the working hours.")
+            .arg(
+                Arg::new("at")
+                    .about("Ends the entry at this datetime instead of prompting.")
+                    .value_name("DATETIME")
+                    .long("at")
+                    .short('a')
+                    .allow_hyphen_values(true)
+            )
         )
         .subcommand(
             new_app("append")
@@ -150,8 +246,9 @@ pub fn parse() -> ArgMatches {
             new_app("switch-to")
             .visible_alias("t")
             .about("Stops clocking on the currently active task, and starts clocking on the specified task.")
-            .arg(task_param())
+            .arg(picked_task_param())
             .arg(at_arg())
+            .arg(force_arg())
             .arg(new_arg())
             .arg(fetch_arg())
             .arg(title_arg())
@@ -161,6 +258,7 @@ pub fn parse() -> ArgMatches {
             .visible_alias("b")
             .about("Stops clocking on the currently active task, and starts clocking on the previous task.")
             .arg(at_arg())
+            .arg(force_arg())
             .arg(
                 Arg::new("index")
                     .about("Switches back to the ith previous task.")
@@ -168,6 +266,365 @@ pub fn parse() -> ArgMatches {
                     .default_value("1")
             )
         )
+        .subcommand(
+            new_app("note")
+            .about("Sets the note of the most recent log entry.")
+            .arg(
+                Arg::new("note")
+                .about("The note, e.g. what was done.")
+                .value_name("NOTE")
+                .required(true)
+            )
+        )
+        .subcommand(
+            new_app("estimate")
+            .about("Sets the expected effort for a task, or for a whole subtree of tasks.")
+            .arg(
+                Arg::new("task")
+                .about("The target task, or the prefix of the subtree if '--subtree' is used.")
+                .value_name("TASK")
+                .required(true)
+                .validator(Task::validate_key)
+            )
+            .arg(
+                Arg::new("duration")
+                .about("Expected effort, e.g. '8h' or '2d4h'.")
+                .value_name("DURATION")
+                .required_unless_present("clear")
+            )
+            .arg(
+                Arg::new("subtree")
+                .about("Sets a budget covering the task and all tasks nested under it.")
+                .long("subtree")
+                .short('s')
+            )
+            .arg(
+                Arg::new("clear")
+                .about("Removes the estimate instead of setting it.")
+                .long("clear")
+                .conflicts_with("duration")
+            )
+        )
+        .subcommand(
+            new_app("budgets")
+            .about("Prints the budgets set on subtrees of tasks and how much of them is used.")
+            .arg(table_format_arg())
+        )
+        .subcommand(
+            new_app("balance")
+            .about("Compares the tracked time against the working schedule, and prints the overtime balance.")
+            .arg(
+                Arg::new("mode")
+                    .about("Specifies whether to compare per day or per week.")
+                    .value_name("MODE")
+                    .long("mode")
+                    .short('m')
+                    .takes_value(true)
+                    .possible_values(&["daily", "weekly"])
+                    .default_value("weekly")
+            )
+            .arg(table_format_arg())
+            .arg(
+                Arg::new("after")
+                    .about("Start of the period. Defaults to the earliest entry.")
+                    .value_name("DATETIME")
+                    .long("after")
+                    .short('a')
+            )
+            .arg(
+                Arg::new("before")
+                    .about("End of the period. Defaults to 'now'.")
+                    .value_name("DATETIME")
+                    .long("before")
+                    .short('b')
+            )
+            .arg(include_archived_arg())
+        )
+        .subcommand(
+            new_app("chart")
+            .about("Draws charts of the tracked time in the terminal.")
+            .setting(AppSettings::SubcommandRequired)
+            .subcommand(
+                new_app("daily")
+                .about("Draws a bar per day, stacked by top-level task, marking days over the working schedule.")
+                .arg(
+                    Arg::new("after")
+                        .about("Start of the period. Defaults to two weeks ago.")
+                        .value_name("DATETIME")
+                        .long("after")
+                        .short('a')
+                )
+                .arg(
+                    Arg::new("before")
+                        .about("End of the period. Defaults to 'now'.")
+                        .value_name("DATETIME")
+                        .long("before")
+                        .short('b')
+                )
+                .arg(include_archived_arg())
+            )
+            .subcommand(
+                new_app("heatmap")
+                .about("Draws a grid of the tracked time per day, one column per week.")
+                .arg(
+                    Arg::new("year")
+                        .about("Calendar year to draw. Defaults to the last 52 weeks.")
+                        .value_name("YEAR")
+                        .long("year")
+                        .short('y')
+                )
+                .arg(include_archived_arg())
+            )
+        )
+        .subcommand(
+            new_app("gaps")
+            .about("Lists the working hours of a day in which nothing was tracked.")
+            .arg(
+                Arg::new("day")
+                    .about("The day to check: 'today', 'yesterday' or YYYY-MM-DD.")
+                    .value_name("DAY")
+                    .long("day")
+                    .default_value("today")
+            )
+            .arg(
+                Arg::new("min")
+                    .about("Ignores gaps shorter than this.")
+                    .value_name("DURATION")
+                    .long("min")
+                    .short('m')
+                    .default_value("5min")
+            )
+            .arg(
+                Arg::new("assign")
+                    .about("Prompts for a task to log each gap to.")
+                    .long("assign")
+            )
+        )
+        .subcommand(
+            new_app("archive")
+            .about("Moves a task, or all tasks under a prefix, to the archive. Archived tasks are left out of the status and reports unless '--include-archived' is used.")
+            .arg(
+                Arg::new("task")
+                .about("The task, or the prefix of the tasks, to archive.")
+                .value_name("TASK")
+                .required(true)
+                .validator(Task::validate_key)
+            )
+        )
+        .subcommand(
+            new_app("rm")
+            .about("Deletes a task and its whole log, after asking for confirmation.")
+            .arg(task_param())
+            .arg(
+                Arg::new("yes")
+                .about("Does not ask for confirmation.")
+                .long("yes")
+                .short('y')
+            )
+        )
+        .subcommand(
+            new_app("doctor")
+            .about("Checks the whole repository for problems: unreadable task files, invalid keys, unsorted logs, entries ending before they start or lasting zero time, several open entries, overlaps, index mismatches and stray files.")
+            .arg(
+                Arg::new("fix")
+                .about("Fixes what can be fixed safely: sorts logs, drops zero-length entries, closes open entries followed by others, and updates the index.")
+                .long("fix")
+            )
+            .arg(
+                Arg::new("json")
+                .about("Prints a JSON summary instead of the report.")
+                .long("json")
+            )
+        )
+        .subcommand(
+            new_app("ui")
+            .about("Opens a full-screen dashboard with the active task, the most recent tasks and the log entries of a day or week. Press 'q' to quit.")
+        )
+        .subcommand(
+            new_app("completions")
+            .about("Prints a completion script for the given shell. Task keys are completed from the index of the data directory in use.")
+            .arg(
+                Arg::new("shell")
+                .about("The shell to complete for.")
+                .value_name("SHELL")
+                .required(true)
+                .possible_values(&["bash", "zsh", "fish"])
+            )
+        )
+        .subcommand(
+            new_app("prompt")
+            .about("Prints the active task for a shell prompt, or nothing when there is none. Reads just the active task saved along with the index, so it stays fast in large repositories.")
+            .arg(
+                Arg::new("format")
+                .about("Template for the line, with the fields of 'status --template', or the name of one under '[templates]' in the configuration.")
+                .value_name("TEMPLATE")
+                .long("format")
+                .short('f')
+                .default_value("{id} {effort:hm}")
+            )
+        )
+        .subcommand(
+            new_app("__complete-keys")
+            .about("Prints the indexed task keys starting with the prefix; used by the completion scripts.")
+            .setting(AppSettings::Hidden)
+            .arg(
+                Arg::new("prefix")
+                .value_name("PREFIX")
+                .setting(ArgSettings::AllowEmptyValues)
+                .default_value("")
+            )
+        )
+        .subcommand(
+            new_app("sync")
+            .about("Merges with the git remote set in the configuration, then pushes. Log entries of tasks changed on both sides are united, and overlapping entries are reported.")
+        )
+        .subcommand(
+            new_app("import")
+            .about("Imports data from other tools into the repository.")
+            .setting(AppSettings::SubcommandRequired)
+            .subcommand(
+                new_app("dit-v1")
+                .about("Imports the tasks of a dit version 1 data directory. Existing tasks are not touched.")
+                .arg(
+                    Arg::new("path")
+                    .about("The dit version 1 data directory, usually '~/.dit'.")
+                    .value_name("PATH")
+                    .value_hint(ValueHint::DirPath)
+                    .required(true)
+                )
+            )
+            .subcommand(
+                new_app("timewarrior")
+                .about("Imports Timewarrior intervals, mapping tags to tasks with the rules in the configuration. Intervals already present are skipped.")
+                .arg(
+                    Arg::new("files")
+                    .about("Output of 'timew export', or '*.data' files from Timewarrior's database. Use '-' for stdin.")
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .required(true)
+                    .multiple(true)
+                )
+            )
+            .subcommand(
+                new_app("org")
+                .about("Imports the CLOCK lines of an org file. Tasks are named after the enclosing headlines, e.g. 'clients/acme/api'. Entries already present are skipped.")
+                .arg(
+                    Arg::new("file")
+                    .about("The org file. Use '-' for stdin.")
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .required(true)
+                )
+            )
+            .subcommand(
+                new_app("json")
+                .about("Merges a document written by 'dit export json'. Local data wins: differing titles, estimates, budgets and log entries are reported as conflicts. Entries already present are skipped.")
+                .arg(
+                    Arg::new("file")
+                    .about("The JSON document. Use '-' for stdin.")
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .required(true)
+                )
+                .arg(
+                    Arg::new("dry-run")
+                    .about("Prints what would be imported, without changing anything.")
+                    .long("dry-run")
+                    .short('n')
+                )
+            )
+            .subcommand(
+                new_app("csv")
+                .about("Imports a CSV report, e.g. from Toggl or Clockify. The project column holds the task key; if it is not a valid one, the first tag that is gets used. Entries already present are skipped.")
+                .arg(
+                    Arg::new("file")
+                    .about("The CSV file. Use '-' for stdin.")
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .required(true)
+                )
+                .arg(csv_mapping_arg())
+            )
+            .subcommand(
+                new_app("timeclock")
+                .about("Imports a ledger/hledger timeclock file. Accounts map to tasks, e.g. 'clients:acme:api' to 'clients/acme/api'. Entries already present are skipped.")
+                .arg(
+                    Arg::new("file")
+                    .about("The timeclock file. Use '-' for stdin.")
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .required(true)
+                )
+            )
+            .subcommand(
+                new_app("ical")
+                .about("Imports calendar events, mapping their summaries to tasks with the rules in the configuration. Events already present, or overlapping existing entries, are skipped.")
+                .arg(
+                    Arg::new("file")
+                    .about("The iCalendar (.ics) file. Use '-' for stdin.")
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .required(true)
+                )
+                .arg(
+                    Arg::new("dry-run")
+                    .about("Prints what would be imported, without changing anything.")
+                    .long("dry-run")
+                    .short('n')
+                )
+                .arg(after_arg())
+                .arg(
+                    before_arg()
+                    .about("Consider only entries from before this date. Defaults to 'now'.")
+                )
+            )
+        )
+        .subcommand(
+            new_app("export")
+            .about("Exports log entries for use in other tools.")
+            .setting(AppSettings::SubcommandRequired)
+            .subcommand(
+                new_app("timewarrior")
+                .about("Prints log entries in the JSON format understood by 'timew import', using task ids as tags.")
+                .arg(after_arg())
+                .arg(before_arg())
+            )
+            .subcommand(
+                new_app("ical")
+                .about("Prints closed log entries as iCalendar events, e.g. for overlaying tracked time on a calendar.")
+                .arg(after_arg())
+                .arg(before_arg())
+            )
+            .subcommand(
+                new_app("org")
+                .about("Prints log entries as org CLOCK lines, under headlines mirroring the task keys.")
+                .arg(
+                    Arg::new("clocktable")
+                    .about("Prints a clocktable-like summary of the effort per headline instead.")
+                    .long("clocktable")
+                    .short('c')
+                )
+                .arg(after_arg())
+                .arg(before_arg())
+            )
+            .subcommand(
+                new_app("json")
+                .about("Prints every task, with its full log, and the budgets as a single versioned JSON document, e.g. for backups.")
+            )
+            .subcommand(
+                new_app("csv")
+                .about("Prints log entries as a CSV report, e.g. in Toggl's or Clockify's layout, with task keys as projects.")
+                .arg(csv_mapping_arg())
+                .arg(after_arg())
+                .arg(before_arg())
+            )
+            .subcommand(
+                new_app("timeclock")
+                .about("Prints log entries as ledger/hledger timeclock 'i'/'o' lines, with accounts mirroring the task keys.")
+                .arg(after_arg())
+                .arg(before_arg())
+            )
+        )
         .subcommand(
             new_app("status")
             .visible_alias("s")
@@ -177,7 +634,7 @@ pub fn parse() -> ArgMatches {
                     .about("Prints just the current task plus the duration for which it has been active.")
                     .long("short")
                     .short('s')
-                    .exclusive(true)
+                    .conflicts_with_all(&["properties", "format", "template"])
             )
             .arg(
                 Arg::new("rebuild-index")
@@ -195,9 +652,12 @@ pub fn parse() -> ArgMatches {
             )
             .arg(
                 properties_arg()
-                    .possible_values(&["id", "title", "start", "end", "effort", "total-effort"])
+                    .possible_values(&["id", "title", "start", "end", "effort", "total-effort", "estimate", "remaining", "progress"])
                     .default_values(&["id", "start", "effort", "total-effort"])
             )
+            .arg(table_format_arg())
+            .arg(template_arg())
+            .arg(include_archived_arg())
         )
         .subcommand(
             new_app("list")
@@ -216,38 +676,27 @@ pub fn parse() -> ArgMatches {
                     .long("mode")
                     .short('m')
                     .takes_value(true)
-                    .possible_values(&["group-by-day", "plain", "daily"])
+                    .possible_values(&["group-by-day", "group-by-week", "plain", "daily"])
                     .default_value("group-by-day")
             )
             .arg(
                 Arg::new("format")
-                    .about("Specifies which format to use when printing.")
+                    .about("Specifies which format to use when printing. Markdown keeps the day or week groups as headings; HTML is a self-contained report with a summary, charts and the entries of each task.")
                     .value_name("FORMAT")
                     .long("format")
                     .short('f')
                     .takes_value(true)
-                    .possible_values(&["table", "json-lines", "csv"])
+                    .possible_values(&["table", "json-lines", "csv", "markdown", "html"])
                     .default_value("table")
             )
             .arg(
                 properties_arg()
-                    .possible_values(&["id", "title", "start", "end", "effort"])
+                    .possible_values(&["id", "title", "start", "end", "effort", "estimate", "remaining", "progress"])
                     .default_values(&["id", "start", "effort"])
             )
-            .arg(
-                Arg::new("after")
-                    .about("Consider only entries from after this date.")
-                    .value_name("DATETIME")
-                    .long("after")
-                    .short('a')
-            )
-            .arg(
-                Arg::new("before")
-                    .about("Consider only entries from before this date.")
-                    .value_name("DATETIME")
-                    .long("before")
-                    .short('b')
-            )
+            .arg(template_arg())
+            .arg(after_arg())
+            .arg(before_arg())
+            .arg(include_archived_arg())
         )
-        .get_matches()
 }
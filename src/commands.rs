@@ -1,19 +1,34 @@
-use anyhow::{bail, Result};
-use chrono::{Date, Duration, FixedOffset};
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, NaiveDate};
 use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
 use std::str::FromStr;
 
-use crate::models::{ListItem, Repository, StatusItem, Task};
-use crate::utils::input::prompt;
-use crate::utils::nice::Nice;
-use crate::utils::tables::{Column, Table};
-use crate::utils::time::Timestamp;
+use dit::charts;
+use dit::config::{Config, CsvMapping};
+use dit::formats::{csv, dit_v1, html, ical, json, org, timeclock, timewarrior, Imported};
+use dit::gaps::{self, Gap};
+use dit::models::{
+    BalanceItem, Budget, BudgetItem, ListItem, LogEntry, Repository, StatusItem, Task,
+};
+use dit::report::{self, group_by_day, group_by_week, total_effort, BalanceMode};
+use dit::utils::input::{confirm, pick, prompt};
+use dit::utils::nice::Nice;
+use dit::utils::output;
+use dit::utils::tables::{Column, Emphasis, Table};
+use dit::utils::template::{Template, Value};
+use dit::utils::time::{now, start_of_day, Timestamp};
+
+use crate::ui;
 
 macro_rules! columns {
-    ($t:ty, $($c:pat => $n:expr, $x:expr),+ $(,)?) => {
+    ($t:ty, $($c:pat => $n:expr, $l:ident, $x:expr),+ $(,)?) => {
         |p| match p {
             $(
-                $c => { Column::<$t>::new($n, $x) },
+                $c => { Column::<$t>::new($n, $x).$l() },
             )+
         }
     };
@@ -26,6 +41,9 @@ pub enum StatusProperties {
     End,
     Effort,
     TotalEffort,
+    Estimate,
+    Remaining,
+    Progress,
 }
 
 pub enum ListProperties {
@@ -34,10 +52,14 @@ pub enum ListProperties {
     Start,
     End,
     Effort,
+    Estimate,
+    Remaining,
+    Progress,
 }
 
 pub enum ListMode {
     GroupByDay,
+    GroupByWeek,
     Plain,
     Daily,
 }
@@ -46,18 +68,66 @@ pub enum ListFormat {
     Table,
     JsonLines,
     Csv,
+    Markdown,
+    Html,
+}
+
+/// How `status`, `budgets` and `balance` print their table.
+pub enum TableFormat {
+    Table,
+    Markdown,
+    Html,
+}
+
+/// How `status` prints the tasks.
+pub struct StatusOptions {
+    /// Prints just the active task, if any.
+    pub short: bool,
+    pub format: TableFormat,
+    pub properties: Vec<StatusProperties>,
+    /// Prints each task with this instead of a table, whatever the format.
+    pub template: Option<Template>,
+}
+
+/// How `list` prints the entries.
+pub struct ListOptions {
+    pub check: bool,
+    pub mode: ListMode,
+    pub format: ListFormat,
+    pub properties: Vec<ListProperties>,
+    /// Prints each entry with this instead of a table, whatever the format.
+    pub template: Option<Template>,
+}
+
+/// What imported entries are checked against before being added.
+#[derive(Clone, Copy, PartialEq)]
+enum Checks {
+    /// At most one entry may be open, across all tasks.
+    OneOpen,
+    /// Also, no entry may overlap another of any task; for sources that know nothing of the
+    /// tracked time, e.g. calendars.
+    NoOverlaps,
+    /// Nothing; for restoring backups, which must come back whole.
+    AsIs,
 }
 
 pub struct Dit {
     pub repo: Box<dyn Repository>,
+    pub config: Config,
 }
 
 impl Dit {
-    pub fn new(repo: Box<dyn Repository>) -> Self {
-        Dit { repo }
+    pub fn new(repo: Box<dyn Repository>, config: Config) -> Self {
+        Dit { repo, config }
     }
 
-    pub fn do_new(&self, key: &str, title: Option<&str>, fetch: bool) -> Result<()> {
+    pub fn do_new(
+        &self,
+        key: &str,
+        title: Option<&str>,
+        estimate: Option<Duration>,
+        fetch: bool,
+    ) -> Result<()> {
         let id = self.repo.resolve_key(key);
 
         if self.repo.exists(&id) {
@@ -70,12 +140,31 @@ impl Dit {
             Some(t) => t.to_string(),
             None => prompt("Title")?,
         };
+        task.data.estimate = estimate;
 
         self.repo
             .save(&task)
             .map(|()| info!("Created: {}", task.id))
     }
 
+    /// Prompts for one of the indexed tasks, the most recent first.
+    pub fn pick_task(&self) -> Result<String> {
+        let status = self.repo.get_status(0);
+        if status.is_empty() {
+            bail!("No tasks to pick from; give a task key");
+        }
+
+        let items: Vec<String> = status
+            .iter()
+            .map(|s| format!("{}  {}", s.id, s.title))
+            .collect();
+
+        match pick("Task", &items).context("No task given")? {
+            Some(i) => Ok(status[i].id.clone()),
+            None => bail!("No task picked"),
+        }
+    }
+
     pub fn do_work_on(&self, key: &str, now: Timestamp) -> Result<()> {
         let id = self.repo.resolve_key(key);
 
@@ -92,16 +181,98 @@ impl Dit {
             .map(|()| info!("Working on: {}", id))
     }
 
-    pub fn do_halt(&self, now: Timestamp) -> Result<()> {
+    /// Unless `force` is set, refuses to log an entry longer than the configured maximum.
+    pub fn do_halt(&self, now: Timestamp, force: bool) -> Result<()> {
         if let Some(id) = self.repo.is_clocked_in() {
-            return self
-                .repo
+            if let Some(entry) = self.too_long_entry(now).filter(|_| !force) {
+                let schedule = &self.config.schedule;
+                bail!(
+                    "Halting would log {} to {}, more than the maximum of {}; pass `--at {}` for \
+                     the end of the working day, or `--force`, or use `dit fix-open`",
+                    (now - entry.start).nice(),
+                    id,
+                    schedule.max_entry.nice(),
+                    schedule.suggest_end(entry.start).format(AT_FORMAT)
+                );
+            }
+            self.repo
                 .clock_out(&id, now)
-                .map(|()| info!("Halted: {}", id));
+                .map(|()| info!("Halted: {}", id))?;
+            return self.check_estimates(&id);
         }
         bail!("Not working on any task");
     }
 
+    /// The running entry, if ending it at `now` would make it longer than the maximum.
+    pub fn too_long_entry(&self, now: Timestamp) -> Option<LogEntry> {
+        self.repo.is_clocked_in()?;
+        self.repo
+            .previous_task(0)
+            .map(|(_, entry)| entry)
+            .filter(|e| self.config.schedule.is_too_long(e.start, now))
+    }
+
+    pub fn do_note(&self, note: &str) -> Result<()> {
+        if let Some((id, _)) = self.repo.previous_task(0) {
+            let mut task = self.repo.load(&id)?;
+            if let Some(entry) = task.data.log.last_mut() {
+                entry.note = Some(note.to_string());
+                return self.repo.save(&task).map(|()| info!("Noted on: {}", id));
+            }
+        }
+        bail!("No previous task to add a note to; rebuild index?")
+    }
+
+    pub fn do_estimate(&self, key: &str, estimate: Option<Duration>, subtree: bool) -> Result<()> {
+        let id = self.repo.resolve_key(key);
+
+        if subtree {
+            let mut budgets = self.repo.load_budgets()?;
+
+            match estimate {
+                Some(estimate) => budgets.insert(id.clone(), Budget { estimate }),
+                None => budgets.remove(&id),
+            };
+
+            return self.repo.save_budgets(&budgets).map(|()| match estimate {
+                Some(e) => info!("Budget set: {} {}", id, e.nice()),
+                None => info!("Budget removed: {}", id),
+            });
+        }
+
+        if !self.repo.exists(&id) {
+            bail!("Task does not exist: {}", id);
+        }
+
+        let mut task = self.repo.load(&id)?;
+        task.data.estimate = estimate;
+
+        self.repo.save(&task).map(|()| match estimate {
+            Some(e) => info!("Estimate set: {} {}", id, e.nice()),
+            None => info!("Estimate removed: {}", id),
+        })
+    }
+
+    pub fn do_budgets(&self, format: TableFormat) -> Result<()> {
+        let items = report::budgets(&*self.repo)?;
+
+        let t = Table::new(vec![
+            Column::<BudgetItem>::new("Prefix", |x| x.prefix.to_string()),
+            Column::<BudgetItem>::new("Estimate", |x| x.estimate.nice()).right(),
+            Column::<BudgetItem>::new("TotalEffort", |x| x.total_effort.nice()).right(),
+            Column::<BudgetItem>::new("Remaining", |x| x.remaining().nice()).right(),
+            Column::<BudgetItem>::new("Progress", |x| percent(x.progress())).right(),
+        ])
+        .emphasis(|x| match x.total_effort > x.estimate {
+            true => Emphasis::Warning,
+            false => Emphasis::Normal,
+        });
+
+        print_table(&t, &items, &format, "Budgets");
+
+        Ok(())
+    }
+
     pub fn do_append(&self) -> Result<()> {
         if let Some((id, entry)) = self.repo.previous_task(0) {
             if entry.is_closed() {
@@ -140,10 +311,10 @@ impl Dit {
 
     pub fn do_status(
         &self,
-        short: bool,
+        options: &StatusOptions,
         rebuild: bool,
         limit: usize,
-        properties: &[StatusProperties],
+        include_archived: bool,
     ) -> Result<()> {
         if rebuild {
             debug!("Rebuilding index");
@@ -151,30 +322,48 @@ impl Dit {
             debug!("Done")
         }
 
-        let status = self.repo.get_status(limit);
+        let status = report::status(&*self.repo, limit, include_archived)?;
 
-        if short {
+        if options.short {
             if let Some(s) = status.first() {
                 if s.log_entry.is_open() {
                     println!("{} {}", s.id, s.effort().nice());
                 }
             }
+        } else if let Some(template) = &options.template {
+            for s in &status {
+                println!("{}", template.render(|f| template_value(s, f))?);
+            }
         } else {
             let t = Table::new(
-                properties
+                options
+                .properties
                 .iter()
                 .map(columns!(StatusItem,
-                        StatusProperties::Id          => "Id",          |x| x.id.to_string(),
-                        StatusProperties::Title       => "Title",       |x| x.title.to_string(),
-                        StatusProperties::Start       => "Start",       |x| x.start().nice(),
-                        StatusProperties::End         => "End",         |x| x.end().nice(),
-                        StatusProperties::Effort      => "Effort",      |x| x.effort().nice(),
-                        StatusProperties::TotalEffort => "TotalEffort", |x| x.total_effort.nice(),
+                        StatusProperties::Id          => "Id",          left,  |x| x.id.to_string(),
+                        StatusProperties::Title       => "Title",       wrap,  |x| x.title.to_string(),
+                        StatusProperties::Start       => "Start",       left,  |x| x.start().nice(),
+                        StatusProperties::End         => "End",         left,  |x| x.end().nice(),
+                        StatusProperties::Effort      => "Effort",      right, |x| x.effort().nice(),
+                        StatusProperties::TotalEffort => "TotalEffort", right, |x| x.total_effort.nice(),
+                        StatusProperties::Estimate    => "Estimate",    right, |x| x.estimate.nice(),
+                        StatusProperties::Remaining   => "Remaining",   right, |x| x.remaining().nice(),
+                        StatusProperties::Progress    => "Progress",    right, |x| percent(x.progress()),
                 ))
                 .collect(),
-            );
+            )
+            .emphasis(|x| emphasis(&x.log_entry, x.remaining()));
 
-            t.print(&status);
+            if let (TableFormat::Table, Some(s)) = (&options.format, status.first()) {
+                if s.log_entry.is_open() && self.config.schedule.is_too_long(s.start(), now()) {
+                    warn!(
+                        "{} has been running for {}; if the timer was forgotten, use `dit fix-open`",
+                        s.id,
+                        s.effort().nice()
+                    );
+                }
+            }
+            print_table(&t, &status, &options.format, "Status");
         }
 
         Ok(())
@@ -182,16 +371,14 @@ impl Dit {
 
     pub fn do_list(
         &self,
-        check: bool,
-        mode: ListMode,
-        format: ListFormat,
-        properties: &[ListProperties],
+        options: &ListOptions,
         after: Option<Timestamp>,
         before: Option<Timestamp>,
+        include_archived: bool,
     ) -> Result<()> {
-        let data = self.repo.get_listing(after, before)?;
+        let data = report::listing(&*self.repo, after, before, include_archived)?;
 
-        if check && data.len() > 1 {
+        if options.check && data.len() > 1 {
             let mut n = 0;
 
             for i in 1..data.len() {
@@ -205,70 +392,1138 @@ impl Dit {
             }
         }
 
+        let groups: Vec<(Option<String>, &[ListItem])> = match options.mode {
+            ListMode::GroupByDay | ListMode::Daily => group_by_day(&data)
+                .map(|(k, x)| (Some(k.nice()), x))
+                .collect(),
+            ListMode::GroupByWeek => group_by_week(&data).map(|(k, x)| (Some(k), x)).collect(),
+            ListMode::Plain => vec![(None, data.as_slice())],
+        };
+        let daily = matches!(options.mode, ListMode::Daily);
+
+        if let Some(template) = &options.template {
+            for (heading, items) in groups {
+                if let Some(h) = heading {
+                    println!("{}: {}", h, total_effort(items).nice());
+                }
+                if daily {
+                    continue;
+                }
+                for x in items {
+                    println!("{}", template.render(|f| template_value(x, f))?);
+                }
+            }
+            return Ok(());
+        }
+
         let t = Table::new(
-            properties
+            options
+                .properties
                 .iter()
                 .map(columns!(ListItem,
-                    ListProperties::Id     => "Id",     |x| x.id.to_string(),
-                    ListProperties::Title  => "Title",  |x| x.title.to_string(),
-                    ListProperties::Start  => "Start",  |x| x.start().nice(),
-                    ListProperties::End    => "End",    |x| x.end().nice(),
-                    ListProperties::Effort => "Effort", |x| x.effort().nice(),
+                    ListProperties::Id        => "Id",        left,  |x| x.id.to_string(),
+                    ListProperties::Title     => "Title",     wrap,  |x| x.title.to_string(),
+                    ListProperties::Start     => "Start",     left,  |x| x.start().nice(),
+                    ListProperties::End       => "End",       left,  |x| x.end().nice(),
+                    ListProperties::Effort    => "Effort",    right, |x| x.effort().nice(),
+                    ListProperties::Estimate  => "Estimate",  right, |x| x.estimate.nice(),
+                    ListProperties::Remaining => "Remaining", right, |x| x.remaining().nice(),
+                    ListProperties::Progress  => "Progress",  right, |x| percent(x.progress()),
                 ))
                 .collect(),
+        )
+        .emphasis(|x| emphasis(&x.log_entry, x.remaining()));
+
+        match options.format {
+            // Grouping does not fit a flat file; each row has its start anyway
+            ListFormat::Csv => return t.print_csv(&data),
+            ListFormat::Html => print!("{}", html::report(&data)),
+            ListFormat::Markdown => {
+                for (heading, items) in groups {
+                    let total = total_effort(items).nice();
+                    match heading {
+                        Some(h) if daily => println!("- {}: {}", h, total),
+                        Some(h) => {
+                            println!("## {}: {}\n", h, total);
+                            t.print_markdown(items);
+                            println!();
+                        }
+                        None => t.print_markdown(items),
+                    }
+                }
+            }
+            ListFormat::Table | ListFormat::JsonLines => {
+                for (heading, items) in groups {
+                    if let Some(h) = heading {
+                        println!("{}: {}", h, total_effort(items).nice());
+                    }
+                    if !daily {
+                        t.print(items);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn do_balance(
+        &self,
+        mode: BalanceMode,
+        format: TableFormat,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+        include_archived: bool,
+    ) -> Result<()> {
+        let data = report::listing(&*self.repo, after, before, include_archived)?;
+
+        let first = match (after, data.last()) {
+            (Some(a), _) => a.naive_local().date(),
+            (None, Some(x)) => x.start().naive_local().date(),
+            (None, None) => bail!("Nothing tracked yet"),
+        };
+        let end = before.unwrap_or_else(now);
+
+        let items = report::balance(&data, &self.config.schedule, first, end, mode)?;
+        let balance = items.last().map_or_else(Duration::zero, |x| x.balance);
+
+        let t = Table::new(vec![
+            Column::<BalanceItem>::new("Period", |x| x.period.to_string()),
+            Column::<BalanceItem>::new("Target", |x| x.target.nice()).right(),
+            Column::<BalanceItem>::new("Tracked", |x| x.tracked.nice()).right(),
+            Column::<BalanceItem>::new("Difference", |x| x.difference().nice()).right(),
+            Column::<BalanceItem>::new("Balance", |x| x.balance.nice()).right(),
+        ]);
+
+        match format {
+            TableFormat::Table => {
+                t.print(&items);
+                println!("Balance: {}", balance.nice());
+            }
+            TableFormat::Markdown => {
+                t.print_markdown(&items);
+                println!("\nBalance: {}", balance.nice());
+            }
+            TableFormat::Html => {
+                let body = format!("<p>Balance: {}</p>\n{}", balance.nice(), t.html(&items));
+                print!("{}", html::page("Balance", &body));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Closes the active entry at `at`, or at a time picked from suggestions based on the working
+    /// hours and the maximum entry length.
+    pub fn do_fix_open(&self, at: Option<Timestamp>) -> Result<()> {
+        let (id, entry) = match (self.repo.is_clocked_in(), self.repo.previous_task(0)) {
+            (Some(id), Some((_, entry))) => (id, entry),
+            _ => bail!("Not working on any task"),
+        };
+        let now = now();
+        let schedule = &self.config.schedule;
+
+        let end = match at {
+            Some(x) => x,
+            None => {
+                if !schedule.is_too_long(entry.start, now) {
+                    info!(
+                        "{} has been running for {}, within the maximum of {}",
+                        id,
+                        (now - entry.start).nice(),
+                        schedule.max_entry.nice()
+                    );
+                    return Ok(());
+                }
+
+                let mut choices = vec![
+                    (
+                        schedule.suggest_end(entry.start),
+                        "end of the working hours",
+                    ),
+                    (entry.start + schedule.max_entry, "maximum entry length"),
+                    (now, "now"),
+                ];
+                choices.dedup_by_key(|(t, _)| *t);
+
+                let items: Vec<String> = choices
+                    .iter()
+                    .map(|(t, why)| {
+                        format!(
+                            "{}  {} logged, {}",
+                            t.format("%a %F %H:%M"),
+                            (*t - entry.start).nice(),
+                            why
+                        )
+                    })
+                    .collect();
+                let p = format!(
+                    "End {}, running since {}",
+                    id,
+                    entry.start.format("%a %F %H:%M")
+                );
+                match pick(&p, &items)? {
+                    Some(i) => choices[i].0,
+                    None => bail!("No end picked; {} is still running", id),
+                }
+            }
+        };
+
+        if end < entry.start || end > now {
+            bail!(
+                "The end must be between {} and now: {}",
+                entry.start.nice(),
+                end.nice()
+            );
+        }
+        self.repo.clock_out(&id, end).map(|()| {
+            info!(
+                "Halted {} at {}, after {}",
+                id,
+                end.nice(),
+                (end - entry.start).nice()
+            )
+        })?;
+        self.check_estimates(&id)
+    }
+
+    /// Charts the effort per day, two weeks up to today unless a period is given.
+    pub fn do_chart_daily(
+        &self,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+        include_archived: bool,
+    ) -> Result<()> {
+        // Like for `list`, nothing from `before` on is considered
+        let last = match before {
+            Some(b) => (b - Duration::nanoseconds(1)).naive_local().date(),
+            None => now().naive_local().date(),
+        };
+        let first = match after {
+            Some(a) => a.naive_local().date(),
+            None => last - Duration::days(13),
+        };
+        if first > last {
+            bail!("Empty period: {} to {}", first, last);
+        }
+
+        let data = report::listing(
+            &*self.repo,
+            Some(start_of_day(first)),
+            Some(before.unwrap_or_else(|| start_of_day(last + Duration::days(1)))),
+            include_archived,
+        )?;
+        let holidays = self.config.schedule.holidays()?;
+        let target = |day: NaiveDate| match holidays.contains(&day) {
+            true => Duration::zero(),
+            false => self.config.schedule.target(day.weekday()),
+        };
+
+        let width = output::width().unwrap_or(80);
+        print!("{}", charts::daily(&data, first, last, target, width));
+
+        Ok(())
+    }
+
+    /// Charts the effort per day of a calendar year, or of the last year.
+    pub fn do_chart_heatmap(&self, year: Option<i32>, include_archived: bool) -> Result<()> {
+        let (first, last) = match year {
+            Some(y) => match (
+                NaiveDate::from_ymd_opt(y, 1, 1),
+                NaiveDate::from_ymd_opt(y, 12, 31),
+            ) {
+                // Padded to whole weeks, the chart reaches into the years around
+                (Some(first), Some(last))
+                    if first.checked_sub_signed(Duration::weeks(1)).is_some()
+                        && last.checked_add_signed(Duration::weeks(1)).is_some() =>
+                {
+                    (first, last)
+                }
+                _ => bail!("Invalid year: {}", y),
+            },
+            None => {
+                let today = now().naive_local().date();
+                (charts::year_before(today), today)
+            }
+        };
+
+        let data = report::listing(
+            &*self.repo,
+            Some(start_of_day(first)),
+            Some(start_of_day(last + Duration::days(1))),
+            include_archived,
+        )?;
+        print!("{}", charts::heatmap(&data, first, last));
+
+        Ok(())
+    }
+
+    /// Lists the working hours of `day` in which nothing was tracked, and with `assign`, prompts
+    /// for a task to log each of them to.
+    pub fn do_gaps(&self, day: NaiveDate, min: Duration, assign: bool) -> Result<()> {
+        let holidays = self.config.schedule.holidays()?;
+        let hours = self.config.schedule.hours_on(day, &holidays);
+        if hours.is_empty() {
+            info!("No working hours on {}", day.format("%a %F"));
+            return Ok(());
+        }
+
+        let gaps = self.find_gaps(day, &hours, min)?;
+        if gaps.is_empty() {
+            info!("No gaps on {}", day.format("%a %F"));
+            return Ok(());
+        }
+
+        let t = Table::new(vec![
+            Column::<Gap>::new("Start", |x| x.start.format("%H:%M").to_string()),
+            Column::<Gap>::new("End", |x| x.end.format("%H:%M").to_string()),
+            Column::<Gap>::new("Length", |x| x.length().nice()).right(),
+        ]);
+        t.print(&gaps);
+
+        let working = hours
+            .iter()
+            .fold(Duration::zero(), |a, (s, e)| a + (*e - *s));
+        let untracked = gaps.iter().fold(Duration::zero(), |a, x| a + x.length());
+        println!(
+            "Untracked: {} of {} working hours on {}",
+            untracked.nice(),
+            working.nice(),
+            day.format("%a %F")
         );
 
-        match mode {
-            ListMode::GroupByDay => {
-                for (key, items) in group_by_day(&data) {
-                    println!("{}: {}", key.nice(), total_effort(items).nice());
-                    t.print(&items);
+        if assign {
+            let status = self.repo.get_status(0);
+            let items: Vec<String> = status
+                .iter()
+                .map(|s| format!("{}  {}", s.id, s.title))
+                .collect();
+
+            for gap in &gaps {
+                let p = format!(
+                    "Task for {}-{} ({}), Esc to skip",
+                    gap.start.format("%H:%M"),
+                    gap.end.format("%H:%M"),
+                    gap.length().nice()
+                );
+                if let Some(i) = pick(&p, &items)? {
+                    let id = &status[i].id;
+                    let mut task = self.repo.load(id)?;
+                    task.data.log.push(LogEntry {
+                        start: gap.start,
+                        end: Some(gap.end),
+                        note: None,
+                    });
+                    task.data.log.sort();
+                    self.repo.save(&task)?;
+                    info!("Logged {} to: {}", gap.length().nice(), id);
                 }
             }
-            ListMode::Daily => {
-                for (key, items) in group_by_day(&data) {
-                    println!("{}: {}", key.nice(), total_effort(items).nice());
+        }
+
+        Ok(())
+    }
+
+    /// The untracked stretches of the working `hours` of `day`, counting archived tasks as tracked.
+    fn find_gaps(
+        &self,
+        day: NaiveDate,
+        hours: &[(Timestamp, Timestamp)],
+        min: Duration,
+    ) -> Result<Vec<Gap>> {
+        // Entries started the day before may run into the working hours
+        let data = report::listing(
+            &*self.repo,
+            Some(start_of_day(day - Duration::days(1))),
+            Some(start_of_day(day + Duration::days(1))),
+            true,
+        )?;
+        Ok(gaps::find(hours, &data, min, now()))
+    }
+
+    /// Moves the task, and the tasks under it, to the archive.
+    pub fn do_archive(&self, key: &str) -> Result<()> {
+        let id = self.repo.resolve_key(key);
+        let prefix = format!("{}/", id);
+
+        let mut ids: Vec<String> = self
+            .repo
+            .list_ids()?
+            .into_iter()
+            .filter(|x| *x == id || x.starts_with(&prefix))
+            .collect();
+        ids.sort();
+
+        if ids.is_empty() {
+            bail!("No task found under: {}", id);
+        }
+        if let Some(active) = self.repo.is_clocked_in() {
+            if ids.contains(&active) {
+                bail!("Cannot archive the active task: {}", active);
+            }
+        }
+
+        for id in ids {
+            self.repo.archive(&id).map(|()| info!("Archived: {}", id))?;
+        }
+        Ok(())
+    }
+
+    pub fn do_rm(&self, key: &str, yes: bool) -> Result<()> {
+        let id = self.repo.resolve_key(key);
+
+        if !self.repo.exists(&id) {
+            bail!("Task does not exist: {}", id);
+        }
+        if self.repo.is_clocked_in().as_ref() == Some(&id) {
+            bail!("Cannot remove the active task: {}", id);
+        }
+
+        let task = self.repo.load(&id)?;
+        let question = format!(
+            "Remove '{}' and its {} log entries ({})?",
+            id,
+            task.data.log.len(),
+            task.total_effort().nice()
+        );
+        if !yes && !confirm(&question)? {
+            info!("Nothing removed");
+            return Ok(());
+        }
+
+        self.repo.remove(&id).map(|()| info!("Removed: {}", id))
+    }
+
+    /// Prints the findings of `report::audit`, failing if any are left unfixed.
+    pub fn do_doctor(&self, fix: bool, json: bool) -> Result<()> {
+        let findings = report::audit(&*self.repo, &self.config.schedule, fix, now())?;
+        let left = findings.iter().filter(|f| !f.fixed).count();
+
+        if json {
+            let summary = serde_json::json!({
+                "ok": left == 0,
+                "problems": findings.len(),
+                "fixed": findings.len() - left,
+                "findings": findings,
+            });
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        } else {
+            for f in findings.iter() {
+                println!(
+                    "{:<16} {} {}{}",
+                    f.check.name(),
+                    f.id.as_deref().unwrap_or("-"),
+                    f.detail,
+                    if f.fixed { " (fixed)" } else { "" }
+                );
+            }
+            match findings.len() {
+                0 => info!("No problems found"),
+                n => info!("{} problems found; {} fixed", n, n - left),
+            }
+        }
+
+        if left > 0 {
+            bail!("{} problems left", left);
+        }
+        Ok(())
+    }
+
+    pub fn do_ui(&self) -> Result<()> {
+        ui::run(self)
+    }
+
+    pub fn do_prompt(&self, template: &Template) -> Result<()> {
+        let active = self
+            .repo
+            .get_status(1)
+            .into_iter()
+            .find(|s| s.log_entry.is_open());
+
+        print_prompt(template, active.as_ref())
+    }
+
+    pub fn do_complete_keys(&self, prefix: &str) -> Result<()> {
+        let mut ids = self.repo.list_ids()?;
+        ids.retain(|id| id.starts_with(prefix));
+        ids.sort();
+
+        for id in ids {
+            println!("{}", id);
+        }
+        Ok(())
+    }
+
+    pub fn do_sync(&self) -> Result<()> {
+        self.repo.sync()?;
+
+        let data = self.repo.get_listing(None, None)?;
+
+        let n = (1..data.len())
+            .filter(|&i| check_overlap(&data[i - 1], &data[i]))
+            .count();
+        if n > 0 {
+            warn!(
+                "{} overlapping entries after merging; see 'dit list --check'",
+                n
+            );
+        }
+
+        info!("Synced");
+        Ok(())
+    }
+
+    pub fn do_import_dit_v1(&self, directory: &Path) -> Result<()> {
+        let conversion = dit_v1::read(directory)?;
+
+        let mut problems = conversion.problems;
+        let mut tasks = 0;
+        let mut entries = 0;
+
+        for task in conversion.tasks {
+            if self.repo.exists(&task.id) {
+                problems.push(format!("{}: task already exists", task.id));
+                continue;
+            }
+            self.repo.save(&task)?;
+            debug!("Imported: {}", task.id);
+
+            tasks += 1;
+            entries += task.data.log.len();
+        }
+
+        for p in problems.iter() {
+            warn!("Not imported: {}", p);
+        }
+
+        debug!("Rebuilding index");
+        self.repo.rebuild_index()?;
+
+        info!("Imported {} tasks with {} log entries", tasks, entries);
+        if !problems.is_empty() {
+            warn!("{} items could not be imported", problems.len());
+        }
+        Ok(())
+    }
+
+    pub fn do_import_timewarrior(&self, files: &[&str]) -> Result<()> {
+        let mut intervals = Vec::new();
+
+        for f in files {
+            let s = read_input(f)?;
+            let mut i = if f.ends_with(".data") {
+                timewarrior::parse_data(&s)
+            } else {
+                timewarrior::parse_export(&s)
+            }
+            .with_context(|| format!("Could not import: {}", f))?;
+            intervals.append(&mut i);
+        }
+
+        let mut problems = Vec::new();
+        let imported =
+            timewarrior::convert(&intervals, &self.config.timewarrior.rules, &mut problems)?;
+
+        self.import_entries(imported, problems, Checks::OneOpen, false)
+    }
+
+    pub fn do_export_timewarrior(
+        &self,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<()> {
+        let data = self.repo.get_listing(after, before)?;
+
+        println!("{}", timewarrior::export(&data)?);
+
+        Ok(())
+    }
+
+    pub fn do_import_org(&self, file: &str) -> Result<()> {
+        let s = read_input(file)?;
+
+        let mut problems = Vec::new();
+        let mut imported = org::parse(&s, &mut problems);
+
+        // Org timestamps have no seconds, so entries exported before start up to a minute later
+        for i in imported.iter_mut() {
+            if !self.repo.exists(&i.id) {
+                continue;
+            }
+            let task = self.repo.load(&i.id)?;
+            let start = i.entry.start;
+            if let Some(x) = task
+                .data
+                .log
+                .iter()
+                .find(|x| (0..60).contains(&(x.start - start).num_seconds()))
+            {
+                i.entry.start = x.start;
+            }
+        }
+
+        self.import_entries(imported, problems, Checks::OneOpen, false)
+    }
+
+    pub fn do_export_org(
+        &self,
+        clocktable: bool,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<()> {
+        let data = self.repo.get_listing(after, before)?;
+
+        if clocktable {
+            print!("{}", org::clocktable(&data));
+        } else {
+            print!("{}", org::export(&data));
+        }
+
+        Ok(())
+    }
+
+    pub fn do_import_timeclock(&self, file: &str) -> Result<()> {
+        let s = read_input(file)?;
+
+        let mut problems = Vec::new();
+        let imported = timeclock::parse(&s, &mut problems);
+
+        self.import_entries(imported, problems, Checks::OneOpen, false)
+    }
+
+    pub fn do_export_timeclock(
+        &self,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<()> {
+        let data = self.repo.get_listing(after, before)?;
+
+        print!("{}", timeclock::export(&data));
+
+        Ok(())
+    }
+
+    pub fn do_import_csv(&self, file: &str, preset: &str) -> Result<()> {
+        let s = read_input(file)?;
+        let mapping = self.get_csv_mapping(preset)?;
+
+        let mut problems = Vec::new();
+        let mut imported = csv::parse(&s, &mapping, &mut problems)?;
+
+        // Exports without a note column write the title for entries without a note
+        if mapping.note.is_none() {
+            // The titles the tasks have, or will get from `import_entries`
+            let mut titles: BTreeMap<String, Option<String>> = BTreeMap::new();
+            for x in imported.iter() {
+                if !titles.contains_key(&x.id) && self.repo.exists(&x.id) {
+                    titles.insert(x.id.clone(), Some(self.repo.load(&x.id)?.data.title));
+                }
+                let title = titles.entry(x.id.clone()).or_insert(None);
+                if title.is_none() {
+                    *title = x.title.clone();
+                }
+            }
+            for x in imported.iter_mut() {
+                if x.entry.note.is_some() && x.entry.note == titles[&x.id] {
+                    x.entry.note = None;
+                }
+            }
+        }
+
+        self.import_entries(imported, problems, Checks::OneOpen, false)
+    }
+
+    pub fn do_export_csv(
+        &self,
+        preset: &str,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<()> {
+        let data = self.repo.get_listing(after, before)?;
+        let mapping = self.get_csv_mapping(preset)?;
+
+        print!("{}", csv::export(&data, &mapping)?);
+
+        Ok(())
+    }
+
+    pub fn do_export_json(&self) -> Result<()> {
+        println!("{}", self.export_json()?);
+
+        Ok(())
+    }
+
+    fn export_json(&self) -> Result<String> {
+        let mut ids = self.repo.list_ids()?;
+        ids.sort();
+
+        let tasks = ids
+            .iter()
+            .map(|id| self.repo.load(id))
+            .collect::<Result<Vec<_>>>()?;
+
+        json::export(tasks, self.repo.load_budgets()?, now())
+    }
+
+    /// Merges a document written by `do_export_json`. Local data wins: differing titles,
+    /// estimates, budgets and log entries are reported as conflicts and left as they are.
+    pub fn do_import_json(&self, file: &str, dry_run: bool) -> Result<()> {
+        let document = json::parse(&read_input(file)?)?;
+
+        let mut conflicts = Vec::new();
+        let mut imported = Vec::new();
+        let mut created = 0;
+
+        for t in document.tasks {
+            let id = t.id;
+
+            let local = if self.repo.exists(&id) {
+                let mut local = self.repo.load(&id)?;
+
+                if local.data.title != t.data.title {
+                    conflicts.push(format!(
+                        "{}: title differs; kept '{}' over '{}'",
+                        id, local.data.title, t.data.title
+                    ));
+                }
+                match (local.data.estimate, t.data.estimate) {
+                    (None, Some(e)) => {
+                        local.data.estimate = Some(e);
+                        if !dry_run {
+                            self.repo.save(&local)?;
+                        }
+                    }
+                    (Some(a), Some(b)) if a != b => conflicts.push(format!(
+                        "{}: estimate differs; kept {} over {}",
+                        id,
+                        a.nice(),
+                        b.nice()
+                    )),
+                    _ => {}
+                }
+                local
+            } else {
+                // Saved up front, so that estimates and tasks without entries come along too
+                let mut task = Task::new(id.clone());
+                task.data.title = t.data.title;
+                task.data.estimate = t.data.estimate;
+                if !dry_run {
+                    self.repo.save(&task)?;
+                }
+                created += 1;
+                task
+            };
+
+            for e in t.data.log {
+                let differs = local.data.log.iter().any(|x| {
+                    is_same_entry(x, &e)
+                        && (x.end != e.end || (e.note.is_some() && x.note != e.note))
+                });
+                if differs {
+                    conflicts.push(format!(
+                        "{} {}: entry differs; kept the local one",
+                        id,
+                        e.start.nice()
+                    ));
+                    continue;
+                }
+                imported.push(Imported {
+                    id: id.clone(),
+                    title: None,
+                    entry: e,
+                });
+            }
+        }
+
+        let mut budgets = self.repo.load_budgets()?;
+        let mut budgets_changed = false;
+
+        for (prefix, b) in document.budgets {
+            match budgets.get(&prefix) {
+                Some(x) if x.estimate != b.estimate => conflicts.push(format!(
+                    "budget {}: estimate differs; kept {} over {}",
+                    prefix,
+                    x.estimate.nice(),
+                    b.estimate.nice()
+                )),
+                Some(_) => {}
+                None => {
+                    budgets.insert(prefix, b);
+                    budgets_changed = true;
                 }
             }
-            ListMode::Plain => t.print(&data),
+        }
+        if budgets_changed && !dry_run {
+            self.repo.save_budgets(&budgets)?;
         }
 
+        for c in conflicts.iter() {
+            warn!("Conflict: {}", c);
+        }
+        match dry_run {
+            true => info!("Would create {} tasks", created),
+            false => info!("Created {} tasks", created),
+        }
+        if !conflicts.is_empty() {
+            warn!("{} conflicts; the local data was kept", conflicts.len());
+        }
+        self.import_entries(imported, Vec::new(), Checks::AsIs, dry_run)
+    }
+
+    pub fn do_export_ical(
+        &self,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<()> {
+        let data = self.repo.get_listing(after, before)?;
+
+        print!("{}", ical::export(&data));
+
+        Ok(())
+    }
+
+    pub fn do_import_ical(
+        &self,
+        file: &str,
+        dry_run: bool,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<()> {
+        let s = read_input(file)?;
+
+        let mut problems = Vec::new();
+        let imported = ical::parse(
+            &s,
+            &self.config.ical.rules,
+            after,
+            before.unwrap_or_else(now),
+            &mut problems,
+        )?;
+
+        self.import_entries(imported, problems, Checks::NoOverlaps, dry_run)
+    }
+
+    /// Adds the entries to their tasks, creating the tasks as needed. Entries already present in
+    /// the task are skipped, so importing the same data again is harmless, except that ones still
+    /// open here are closed if they ended in the import; entries failing the `checks` are reported
+    /// instead of imported.
+    fn import_entries(
+        &self,
+        imported: Vec<Imported>,
+        mut problems: Vec<String>,
+        checks: Checks,
+        dry_run: bool,
+    ) -> Result<()> {
+        let min_start = imported.iter().map(|i| i.entry.start).min();
+        let mut taken: Vec<(String, LogEntry)> = match (checks, min_start) {
+            (Checks::NoOverlaps, Some(t)) => self
+                .repo
+                .get_listing(Some(t - Duration::days(1)), None)?
+                .into_iter()
+                .map(|x| (x.id, x.log_entry))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut by_task: BTreeMap<String, (Option<String>, Vec<_>)> = BTreeMap::new();
+
+        for i in imported {
+            let t = by_task.entry(i.id).or_insert_with(|| (None, Vec::new()));
+            if t.0.is_none() {
+                t.0 = i.title;
+            }
+            t.1.push(i.entry);
+        }
+
+        let mut clocked_in = self.repo.is_clocked_in();
+        let mut added = 0;
+        let mut closed = 0;
+        let mut skipped = 0;
+
+        for (id, (title, entries)) in by_task {
+            let mut task = if self.repo.exists(&id) {
+                self.repo.load(&id)?
+            } else {
+                let mut t = Task::new(id.clone());
+                t.data.title = title.unwrap_or_else(|| id.clone());
+                t
+            };
+
+            let n = task.data.log.len();
+            let mut changed = false;
+
+            for e in entries {
+                if let Some(x) = task.data.log.iter_mut().find(|x| is_same_entry(x, &e)) {
+                    // E.g. Timewarrior was still tracking it when the data was last imported
+                    if x.is_open() && !e.is_open() && checks != Checks::AsIs {
+                        x.end = e.end;
+                        if clocked_in.as_ref() == Some(&id) {
+                            clocked_in = None;
+                        }
+                        changed = true;
+                        closed += 1;
+                    } else {
+                        skipped += 1;
+                    }
+                    continue;
+                }
+                let overlapped = match checks {
+                    Checks::NoOverlaps => taken.iter().find(|(_, x)| x.overlaps(&e)),
+                    Checks::OneOpen | Checks::AsIs => None,
+                };
+                if let Some((other, _)) = overlapped {
+                    problems.push(format!(
+                        "{} {}: overlaps an entry of: {}",
+                        id,
+                        e.start.nice(),
+                        other
+                    ));
+                    continue;
+                }
+                if e.is_open() && checks != Checks::AsIs {
+                    if let Some(c) = &clocked_in {
+                        problems.push(format!(
+                            "{} {}: already working on: {}",
+                            id,
+                            e.start.nice(),
+                            c
+                        ));
+                        continue;
+                    }
+                    clocked_in = Some(id.clone());
+                }
+                if dry_run {
+                    info!("{} | {} | {}", e.start.nice(), e.end.nice(), id);
+                }
+                if checks == Checks::NoOverlaps {
+                    taken.push((id.clone(), e.clone()));
+                }
+                task.data.log.push(e);
+            }
+
+            if task.data.log.len() == n && !changed {
+                continue;
+            }
+
+            added += task.data.log.len() - n;
+
+            if dry_run {
+                continue;
+            }
+
+            task.data.log.sort();
+
+            self.repo.save(&task)?;
+            debug!("Imported: {}", id);
+        }
+
+        for p in problems.iter() {
+            warn!("Not imported: {}", p);
+        }
+
+        match dry_run {
+            true => info!(
+                "Would import {} log entries and close {} open ones; skipped {} already present",
+                added, closed, skipped
+            ),
+            false => info!(
+                "Imported {} log entries and closed {} open ones; skipped {} already present",
+                added, closed, skipped
+            ),
+        }
+        if !problems.is_empty() {
+            warn!("{} entries could not be imported", problems.len());
+        }
+        Ok(())
+    }
+
+    /// Mappings from the configuration take precedence over the built-in presets.
+    fn get_csv_mapping(&self, name: &str) -> Result<CsvMapping> {
+        match self.config.csv.mappings.get(name) {
+            Some(m) => Ok(m.clone()),
+            None => csv::preset(name).with_context(|| format!("Unknown CSV mapping: {}", name)),
+        }
+    }
+
+    /// Warns if the last clock out made the task, or any budget covering it, go over its
+    /// estimate.
+    fn check_estimates(&self, id: &String) -> Result<()> {
+        let task = self.repo.load(id)?;
+
+        let last = match task.data.log.last() {
+            Some(e) => e.effort(),
+            None => return Ok(()),
+        };
+
+        if let Some(estimate) = task.data.estimate {
+            let total = task.total_effort();
+            if total > estimate && total - last <= estimate {
+                warn!(
+                    "Task is over its estimate: {} ({} of {})",
+                    id,
+                    total.nice(),
+                    estimate.nice()
+                );
+            }
+        }
+
+        for b in report::budgets(&*self.repo)? {
+            if !Task::is_under(id, &b.prefix) {
+                continue;
+            }
+            if b.total_effort > b.estimate && b.total_effort - last <= b.estimate {
+                warn!(
+                    "Budget is over its estimate: {} ({} of {})",
+                    b.prefix,
+                    b.total_effort.nice(),
+                    b.estimate.nice()
+                );
+            }
+        }
         Ok(())
     }
 }
 
-fn check_overlap(x: &ListItem, y: &ListItem) -> bool {
-    let overlaps = y.end().map(|e| e > x.start()).unwrap_or(false);
+/// Whether both are the same entry, i.e. start at the same time.
+fn is_same_entry(x: &LogEntry, y: &LogEntry) -> bool {
+    x.start == y.start
+}
 
-    if overlaps {
-        warn!("Overlapping entries:");
-        warn!("    {} | {} | {}", x.start().nice(), x.end().nice(), x.id.to_string());
-        warn!("    {} | {} | {}", y.start().nice(), y.end().nice(), y.id.to_string());
+/// Reads the whole file, or stdin if it is '-'.
+fn read_input(f: &str) -> Result<String> {
+    if f == "-" {
+        let mut s = String::new();
+        io::stdin()
+            .read_to_string(&mut s)
+            .context("Could not read stdin")?;
+        return Ok(s);
     }
-    overlaps
+    fs::read_to_string(f).with_context(|| format!("Could not read file: {}", f))
+}
+
+/// Timestamps as accepted by `--at`.
+const AT_FORMAT: &str = "%Y-%m-%d-%H:%M";
+
+/// The fields of `list`, `status` and `prompt` templates.
+pub const TEMPLATE_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "start",
+    "end",
+    "effort",
+    "total-effort",
+    "estimate",
+    "remaining",
+    "progress",
+    "note",
+];
+
+/// Prints the active task with the template, or nothing when there is none.
+pub fn print_prompt(template: &Template, active: Option<&StatusItem>) -> Result<()> {
+    if let Some(s) = active {
+        println!("{}", template.render(|f| template_value(s, f))?);
+    }
+    Ok(())
 }
 
-fn total_effort(x: &[ListItem]) -> Duration {
-    x.iter().fold(Duration::seconds(0), |a, x| a + x.effort())
+/// What a template can show of an item, shared by the `status` item and the `list` rows.
+trait TemplateItem {
+    fn id(&self) -> &str;
+    fn title(&self) -> &str;
+    fn log_entry(&self) -> &LogEntry;
+    fn total_effort(&self) -> Duration;
+    fn estimate(&self) -> Option<Duration>;
+    fn remaining(&self) -> Option<Duration>;
+    fn progress(&self) -> Option<i64>;
 }
 
-// based on: https://stackoverflow.com/a/50392400
-fn group_by_day(x: &[ListItem]) -> impl Iterator<Item = (Date<FixedOffset>, &[ListItem])> {
-    let key = |z: &ListItem| z.log_entry.start.date();
+macro_rules! template_item {
+    ($($t:ty),+) => {
+        $(
+            impl TemplateItem for $t {
+                fn id(&self) -> &str {
+                    &self.id
+                }
 
-    let mut slice_start = 0;
+                fn title(&self) -> &str {
+                    &self.title
+                }
 
-    (1..x.len() + 1).flat_map(move |i| {
-        let k = key(&x[i - 1]);
+                fn log_entry(&self) -> &LogEntry {
+                    &self.log_entry
+                }
 
-        if i == x.len() || k != key(&x[i]) {
-            let start = slice_start;
-            slice_start = i;
-            Some((k, &x[start..i]))
-        } else {
-            None
-        }
-    })
+                fn total_effort(&self) -> Duration {
+                    self.total_effort
+                }
+
+                fn estimate(&self) -> Option<Duration> {
+                    self.estimate
+                }
+
+                fn remaining(&self) -> Option<Duration> {
+                    <$t>::remaining(self)
+                }
+
+                fn progress(&self) -> Option<i64> {
+                    <$t>::progress(self)
+                }
+            }
+        )+
+    };
+}
+
+template_item!(StatusItem, ListItem);
+
+fn template_value(x: &impl TemplateItem, field: &str) -> Value {
+    let log_entry = x.log_entry();
+    match field {
+        "id" => Value::from(x.id().to_string()),
+        "title" => Value::from(x.title().to_string()),
+        "start" => Value::from(log_entry.start),
+        "end" => Value::from(log_entry.end),
+        "effort" => Value::from(log_entry.effort()),
+        "total-effort" => Value::from(x.total_effort()),
+        "estimate" => Value::from(x.estimate()),
+        "remaining" => Value::from(x.remaining()),
+        "progress" => Value::from(x.progress().map(|p| format!("{}%", p))),
+        "note" => Value::from(log_entry.note.clone()),
+        _ => Value::Empty,
+    }
+}
+
+fn print_table<R>(t: &Table<R>, data: &[R], format: &TableFormat, title: &str) {
+    match format {
+        TableFormat::Table => t.print(data),
+        TableFormat::Markdown => t.print_markdown(data),
+        TableFormat::Html => print!("{}", html::page(title, &t.html(data))),
+    }
+}
+
+/// Highlights the entry being clocked, and tasks over their estimate.
+fn emphasis(entry: &LogEntry, remaining: Option<Duration>) -> Emphasis {
+    if entry.is_open() {
+        Emphasis::Active
+    } else if remaining.map(|r| r < Duration::zero()).unwrap_or(false) {
+        Emphasis::Warning
+    } else {
+        Emphasis::Normal
+    }
+}
+
+fn percent(x: Option<i64>) -> String {
+    x.map(|p| format!("{}%", p)).unwrap_or_default()
+}
+
+fn check_overlap(x: &ListItem, y: &ListItem) -> bool {
+    let overlaps = y.end().map(|e| e > x.start()).unwrap_or(false);
+
+    if overlaps {
+        warn!("Overlapping entries:");
+        warn!("    {} | {} | {}", x.start().nice(), x.end().nice(), x.id.to_string());
+        warn!("    {} | {} | {}", y.start().nice(), y.end().nice(), y.id.to_string());
+    }
+    overlaps
 }
 
 impl FromStr for StatusProperties {
@@ -282,6 +1537,9 @@ impl FromStr for StatusProperties {
             "end" => Ok(Self::End),
             "effort" => Ok(Self::Effort),
             "total-effort" => Ok(Self::TotalEffort),
+            "estimate" => Ok(Self::Estimate),
+            "remaining" => Ok(Self::Remaining),
+            "progress" => Ok(Self::Progress),
             _ => bail!("Invalid task field: {}", s),
         }
     }
@@ -297,6 +1555,9 @@ impl FromStr for ListProperties {
             "start" => Ok(Self::Start),
             "end" => Ok(Self::End),
             "effort" => Ok(Self::Effort),
+            "estimate" => Ok(Self::Estimate),
+            "remaining" => Ok(Self::Remaining),
+            "progress" => Ok(Self::Progress),
             _ => bail!("Invalid task field: {}", s),
         }
     }
@@ -308,6 +1569,7 @@ impl FromStr for ListMode {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "group-by-day" => Ok(Self::GroupByDay),
+            "group-by-week" => Ok(Self::GroupByWeek),
             "plain" => Ok(Self::Plain),
             "daily" => Ok(Self::Daily),
             _ => bail!("Invalid list mode: {}", s),
@@ -323,7 +1585,192 @@ impl FromStr for ListFormat {
             "table" => Ok(Self::Table),
             "json-lines" => Ok(Self::JsonLines),
             "csv" => Ok(Self::Csv),
+            "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
             _ => bail!("Invalid list format: {}", s),
         }
     }
 }
+
+impl FromStr for TableFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            _ => bail!("Invalid format: {}", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{Duration, NaiveDate, Utc};
+    use dit::config::Config;
+    use dit::formats::{csv, org};
+    use dit::models::{LogEntry, Task};
+    use dit::repository::toml::Repo;
+    use dit::utils::time::parse_timestamp;
+    use std::collections::HashSet;
+    use std::fs;
+
+    use super::Dit;
+    use crate::testing::TempDir;
+
+    /// A `Dit` over a new, empty repository, which is removed along with the returned directory.
+    fn dit(name: &str) -> (Dit, TempDir) {
+        let dir = TempDir::new(name);
+        let repo = Repo::new(dir.path().to_path_buf()).unwrap();
+        (Dit::new(Box::new(repo), Config::default()), dir)
+    }
+
+    fn entry(start: &str, end: Option<&str>) -> LogEntry {
+        LogEntry {
+            start: parse_timestamp(start).unwrap(),
+            end: end.and_then(parse_timestamp),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let (source, dir) = dit("json-source");
+        let (target, _target_dir) = dit("json-target");
+
+        // Overlapping entries, and two open ones, are restored as they are
+        let mut foo = Task::new("foo".to_string());
+        foo.data
+            .log
+            .push(entry("2021-03-01-09:00", Some("2021-03-01-12:00")));
+        foo.data.log.push(entry("2021-03-02-09:00", None));
+        let mut bar = Task::new("bar".to_string());
+        bar.data
+            .log
+            .push(entry("2021-03-01-10:00", Some("2021-03-01-11:00")));
+        bar.data.log.push(entry("2021-03-02-10:00", None));
+        source.repo.save(&foo).unwrap();
+        source.repo.save(&bar).unwrap();
+
+        let file = dir.path().join("export.json");
+        fs::write(&file, source.export_json().unwrap()).unwrap();
+        target
+            .do_import_json(file.to_str().unwrap(), false)
+            .unwrap();
+
+        let times = |t: &Task| -> Vec<_> { t.data.log.iter().map(|e| (e.start, e.end)).collect() };
+        for t in &[foo, bar] {
+            assert_eq!(times(&target.repo.load(&t.id).unwrap()), times(t));
+        }
+
+        // Importing again changes nothing
+        target
+            .do_import_json(file.to_str().unwrap(), false)
+            .unwrap();
+        assert_eq!(
+            target.repo.load(&"foo".to_string()).unwrap().data.log.len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let (source, dir) = dit("csv-source");
+        let (target, _target_dir) = dit("csv-target");
+
+        let mut foo = Task::new("foo".to_string());
+        foo.data.title = "The foo".to_string();
+        foo.data
+            .log
+            .push(entry("2021-03-01-09:00", Some("2021-03-01-10:00")));
+        let mut noted = entry("2021-03-01-11:00", Some("2021-03-01-12:00"));
+        noted.note = Some("Reviewing".to_string());
+        foo.data.log.push(noted);
+        source.repo.save(&foo).unwrap();
+
+        let listing = source.repo.get_listing(None, None).unwrap();
+        let file = dir.path().join("export.csv");
+        fs::write(
+            &file,
+            csv::export(&listing, &csv::preset("toggl").unwrap()).unwrap(),
+        )
+        .unwrap();
+        target
+            .do_import_csv(file.to_str().unwrap(), "toggl")
+            .unwrap();
+
+        // The title comes from the first description; notes equal to it were written for none
+        let task = target.repo.load(&foo.id).unwrap();
+        assert_eq!(task.data.title, "The foo");
+        let notes: Vec<_> = task.data.log.iter().map(|e| e.note.clone()).collect();
+        assert_eq!(notes, vec![None, Some("Reviewing".to_string())]);
+    }
+
+    #[test]
+    fn test_timewarrior_closes_open_entries() {
+        let (dit, dir) = dit("timew");
+
+        // Imported while Timewarrior was still tracking it
+        let mut foo = Task::new("foo".to_string());
+        foo.data.log.push(entry("2021-03-01-09:00", None));
+        dit.repo.save(&foo).unwrap();
+
+        let timew = |x: &str| {
+            let t = parse_timestamp(x).unwrap().with_timezone(&Utc);
+            t.format("%Y%m%dT%H%M%SZ").to_string()
+        };
+        let line = format!(
+            "inc {} - {} # foo\n",
+            timew("2021-03-01-09:00"),
+            timew("2021-03-01-10:30")
+        );
+        let file = dir.path().join("export.data");
+        fs::write(&file, line).unwrap();
+        dit.do_import_timewarrior(&[file.to_str().unwrap()])
+            .unwrap();
+
+        let log = dit.repo.load(&foo.id).unwrap().data.log;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].end, parse_timestamp("2021-03-01-10:30"));
+        assert_eq!(dit.repo.is_clocked_in(), None);
+    }
+
+    #[test]
+    fn test_org_round_trip() {
+        let (dit, dir) = dit("org");
+
+        // Org leaves out the seconds
+        let mut foo = Task::new("foo".to_string());
+        let mut e = entry("2021-03-01-09:00", Some("2021-03-01-10:00"));
+        e.start += Duration::seconds(42);
+        foo.data.log.push(e);
+        dit.repo.save(&foo).unwrap();
+
+        let listing = dit.repo.get_listing(None, None).unwrap();
+        let file = dir.path().join("export.org");
+        fs::write(&file, org::export(&listing)).unwrap();
+        dit.do_import_org(file.to_str().unwrap()).unwrap();
+
+        assert_eq!(dit.repo.load(&foo.id).unwrap().data.log.len(), 1);
+    }
+
+    #[test]
+    fn test_gaps_count_archived_tasks() {
+        let (dit, _dir) = dit("gaps");
+        let day = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+        let hours = dit.config.schedule.hours_on(day, &HashSet::new());
+        let gaps = |dit: &Dit| dit.find_gaps(day, &hours, Duration::minutes(15)).unwrap();
+        assert_eq!(gaps(&dit).len(), 2);
+
+        // Still tracked once the task is archived
+        let mut foo = Task::new("foo".to_string());
+        foo.data
+            .log
+            .push(entry("2021-03-01-08:00", Some("2021-03-01-17:00")));
+        dit.repo.save(&foo).unwrap();
+        dit.do_archive("foo").unwrap();
+        assert!(gaps(&dit).is_empty());
+    }
+}
@@ -0,0 +1,421 @@
+//! Shell completion scripts for the clap `App` of `cli::build`. The zsh and fish ones are made by
+//! clap_generate, while bash keeps a generator of its own, as the one of clap_generate 3.0.0-beta.2
+//! panics on subcommand names with hyphens. Arguments named `task` are completed dynamically with
+//! the keys printed by the hidden `__complete-keys` subcommand, passing on any `--directory` given
+//! on the command line.
+
+use anyhow::{bail, Result};
+use clap::{App, AppSettings, Arg, ArgSettings};
+use clap_generate::generators::{Fish, Zsh};
+use clap_generate::Generator;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::str::FromStr;
+
+lazy_static! {
+    /// A positional of the zsh script, e.g. `'::title -- The title.:' \`.
+    static ref ZSH_POSITIONAL_RE: Regex = Regex::new(
+        r"^'(?P<arity>\*?:?:)(?P<name>[^: ]+)(?P<help>(?: -- (?:[^:\\]|\\.)*)?):(?P<action>.*)' \\$"
+    )
+    .unwrap();
+    /// A case dispatching to a subcommand in the zsh script, e.g. `(work-on)`.
+    static ref ZSH_CASE_RE: Regex = Regex::new(r"^(?P<indent>\s*)\((?P<name>[\w-]+)\)$").unwrap();
+    static ref FISH_SEEN_RE: Regex =
+        Regex::new(r"__fish_seen_subcommand_from (?P<name>[\w-]+)").unwrap();
+}
+
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => bail!("Unsupported shell: {}", s),
+        }
+    }
+}
+
+/// A command of the tree, with the names of the subcommands leading to it, e.g. `["import", "json"]`.
+struct Command<'a, 'h> {
+    path: Vec<&'a str>,
+    app: &'a App<'h>,
+}
+
+impl<'a, 'h> Command<'a, 'h> {
+    /// Identifies the command in function names and states, e.g. `dit__import__json`.
+    fn ident(&self, bin: &str) -> String {
+        let mut parts = vec![bin];
+        parts.extend(self.path.iter());
+        parts.join("__")
+    }
+
+    fn subcommands(&self) -> Vec<&'a App<'h>> {
+        self.app
+            .get_subcommands()
+            .filter(|s| !s.is_set(AppSettings::Hidden))
+            .collect()
+    }
+
+    fn options(&self, globals: &[&'a Arg<'h>]) -> Vec<&'a Arg<'h>> {
+        let mut options: Vec<_> = self
+            .app
+            .get_arguments()
+            .filter(|a| !is_positional(a) && !a.is_set(ArgSettings::Hidden))
+            .collect();
+        if !self.path.is_empty() {
+            options.extend(globals.iter());
+        }
+        options
+    }
+
+    fn positionals(&self) -> Vec<&'a Arg<'h>> {
+        self.app
+            .get_arguments()
+            .filter(|a| is_positional(a))
+            .collect()
+    }
+
+    fn takes_task(&self) -> bool {
+        self.positionals().iter().any(|a| is_task(a))
+    }
+}
+
+pub fn generate(mut app: App, shell: Shell) -> String {
+    let bin = app.get_name().to_string();
+
+    let script = match shell {
+        Shell::Bash => String::new(),
+        Shell::Zsh => clap_script::<Zsh>(&mut app, &bin),
+        Shell::Fish => clap_script::<Fish>(&mut app, &bin),
+    };
+
+    let mut commands = Vec::new();
+    walk(&app, Vec::new(), &mut commands);
+    let hidden = hidden(&app);
+
+    match shell {
+        Shell::Bash => {
+            // Every argument of the top-level command is global
+            let globals: Vec<_> = app.get_arguments().collect();
+            bash(&bin, &commands, &globals)
+        }
+        Shell::Zsh => zsh(&bin, &script, &commands, &hidden),
+        Shell::Fish => fish(&bin, &script, &commands, &hidden),
+    }
+}
+
+fn clap_script<G: Generator>(app: &mut App, bin: &str) -> String {
+    let mut out = Vec::new();
+    clap_generate::generate::<G, _>(app, bin, &mut out);
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn walk<'a, 'h>(app: &'a App<'h>, path: Vec<&'a str>, out: &mut Vec<Command<'a, 'h>>) {
+    out.push(Command {
+        path: path.clone(),
+        app,
+    });
+    for s in app.get_subcommands() {
+        if s.is_set(AppSettings::Hidden) {
+            continue;
+        }
+        let mut p = path.clone();
+        p.push(s.get_name());
+        walk(s, p, out);
+    }
+}
+
+fn bash<'a, 'h>(bin: &str, commands: &[Command<'a, 'h>], globals: &[&'a Arg<'h>]) -> String {
+    let mut transitions = String::new();
+    let mut cases = String::new();
+
+    for c in commands {
+        let ident = c.ident(bin);
+
+        for s in c.subcommands() {
+            let target = format!("{}__{}", ident, s.get_name());
+            for name in names(s) {
+                transitions.push_str(&format!(
+                    "            {}__{}) cmd=\"{}\" ;;\n",
+                    ident, name, target
+                ));
+            }
+        }
+
+        let options = c.options(globals);
+
+        let mut values = String::new();
+        for a in options.iter().filter(|a| a.is_set(ArgSettings::TakesValue)) {
+            let action = match a.get_possible_values() {
+                Some(v) => format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\")); return ;;",
+                    v.join(" ")
+                ),
+                None => "return ;;".to_string(),
+            };
+            values.push_str(&format!(
+                "                {}) {}\n",
+                switches(a).join("|"),
+                action
+            ));
+        }
+
+        let mut flags: Vec<String> = options.iter().flat_map(|a| switches(a)).collect();
+        flags.push("--help".to_string());
+
+        let words = if c.takes_task() {
+            format!(
+                "COMPREPLY=($({} \"${{dir[@]}}\" __complete-keys \"${{cur}}\" 2>/dev/null))",
+                bin
+            )
+        } else {
+            let mut words: Vec<&str> = c.subcommands().iter().flat_map(|s| names(s)).collect();
+            for p in c.positionals() {
+                words.extend(p.get_possible_values().unwrap_or_default());
+            }
+            format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                words.join(" ")
+            )
+        };
+
+        cases.push_str(&format!(
+            r#"        {ident})
+            case "${{prev}}" in
+{values}            esac
+            if [[ "${{cur}}" == -* ]]; then
+                COMPREPLY=($(compgen -W "{flags}" -- "${{cur}}"))
+            else
+                {words}
+            fi
+            ;;
+"#,
+            ident = ident,
+            values = values,
+            flags = flags.join(" "),
+            words = words,
+        ));
+    }
+
+    format!(
+        r#"_{bin}() {{
+    local cur prev cmd i
+    local -a dir
+    COMPREPLY=()
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    cmd="{bin}"
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            -d|--directory) dir=(--directory "${{COMP_WORDS[i+1]}}") ;;
+        esac
+        case "${{cmd}}__${{COMP_WORDS[i]}}" in
+{transitions}        esac
+    done
+
+    case "${{cmd}}" in
+{cases}    esac
+}}
+
+complete -o default -F _{bin} {bin}
+"#,
+        bin = bin,
+        transitions = transitions,
+        cases = cases,
+    )
+}
+
+/// Hooks the script of clap_generate up to the task keys, and makes up for what its 3.0.0-beta.2
+/// release misses: quoting the help of positionals, aliases and hidden subcommands.
+fn zsh<'a, 'h>(bin: &str, script: &str, commands: &[Command<'a, 'h>], hidden: &[&str]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    for l in script.lines() {
+        if hidden.iter().any(|h| l.starts_with(&format!("\"{}:", h))) {
+            continue;
+        }
+        if let Some(m) = ZSH_POSITIONAL_RE.captures(l) {
+            let action = match &m["name"] {
+                "task" => format!("__{}_keys", bin),
+                _ => m["action"].to_string(),
+            };
+            let spec = format!("{}{}{}:{}", &m["arity"], &m["name"], &m["help"], action);
+            lines.push(format!("{} \\", zsh_quote(&spec)));
+            continue;
+        }
+        if let Some(m) = ZSH_CASE_RE.captures(l) {
+            if let Some(names) = aliased(commands, &m["name"]) {
+                lines.push(format!("{}({})", &m["indent"], names.join("|")));
+                continue;
+            }
+        }
+        lines.push(l.to_string());
+    }
+
+    // The keys function goes right after `#compdef`
+    let keys = format!(
+        r#"
+__{bin}_keys() {{
+    local -a keys dir line
+    local i
+    line=(${{(z)BUFFER}})
+    for ((i = 1; i < ${{#line}}; i++)); do
+        case ${{line[i]}} in
+            (-d|--directory) dir=(--directory ${{line[i+1]}}) ;;
+        esac
+    done
+    keys=(${{(f)"$({bin} $dir __complete-keys "$PREFIX" 2>/dev/null)"}})
+    compadd -a keys
+}}"#,
+        bin = bin
+    );
+    lines.insert(1, keys);
+
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+fn fish<'a, 'h>(bin: &str, script: &str, commands: &[Command<'a, 'h>], hidden: &[&str]) -> String {
+    let mut lines: Vec<String> = script
+        .lines()
+        .filter(|l| !hidden.iter().any(|h| l.contains(h)))
+        .map(|l| {
+            FISH_SEEN_RE
+                .replace_all(l, |m: &Captures| {
+                    let names = aliased(commands, &m["name"]).unwrap_or_else(|| vec![&m["name"]]);
+                    format!("__fish_seen_subcommand_from {}", names.join(" "))
+                })
+                .to_string()
+        })
+        .collect();
+
+    for c in commands.iter().filter(|c| c.takes_task()) {
+        // Matches once the whole path was typed, whatever the aliases used
+        let condition: Vec<String> = c
+            .path
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                let app = commands
+                    .iter()
+                    .find(|x| x.path == c.path[..=i])
+                    .unwrap()
+                    .app;
+                format!("__fish_seen_subcommand_from {}", names(app).join(" "))
+            })
+            .collect();
+        lines.push(format!(
+            "complete -c {} -n \"{}\" -f -a \"(__{}_keys)\"",
+            bin,
+            condition.join("; and "),
+            bin
+        ));
+    }
+
+    format!(
+        r#"function __{bin}_keys
+    set -l dir
+    set -l tokens (commandline -opc)
+    for i in (seq (count $tokens))
+        if contains -- $tokens[$i] -d --directory
+            set dir --directory $tokens[(math $i + 1)]
+        end
+    end
+    {bin} $dir __complete-keys (commandline -ct) 2>/dev/null
+end
+
+{lines}
+"#,
+        bin = bin,
+        lines = lines.join("\n"),
+    )
+}
+
+fn zsh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// The names of the subcommand called `name`, if it has aliases.
+fn aliased<'a, 'h>(commands: &[Command<'a, 'h>], name: &str) -> Option<Vec<&'a str>> {
+    commands
+        .iter()
+        .filter(|c| !c.path.is_empty())
+        .map(|c| names(c.app))
+        .find(|n| n[0] == name && n.len() > 1)
+}
+
+/// The names of the hidden subcommands, which the scripts leave out.
+fn hidden<'a>(app: &'a App) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    for s in app.get_subcommands() {
+        match s.is_set(AppSettings::Hidden) {
+            true => names.push(s.get_name()),
+            false => names.extend(hidden(s)),
+        }
+    }
+    names
+}
+
+/// The name of a subcommand followed by its visible aliases.
+fn names<'a>(app: &'a App) -> Vec<&'a str> {
+    let mut names = vec![app.get_name()];
+    names.extend(app.get_visible_aliases());
+    names
+}
+
+fn switches(a: &Arg) -> Vec<String> {
+    a.get_short()
+        .map(|s| format!("-{}", s))
+        .into_iter()
+        .chain(a.get_long().map(|l| format!("--{}", l)))
+        .collect()
+}
+
+fn is_positional(a: &Arg) -> bool {
+    a.get_short().is_none() && a.get_long().is_none()
+}
+
+fn is_task(a: &Arg) -> bool {
+    a.get_name() == "task"
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{generate, Shell};
+    use crate::cli;
+
+    #[test]
+    fn test_generate() {
+        let bash = generate(cli::build(), Shell::Bash);
+        assert!(bash.contains("dit__import__json)"));
+        assert!(bash.contains("dit__w) cmd=\"dit__work-on\" ;;"));
+        assert!(bash.contains("__complete-keys"));
+        assert!(!bash.contains("dit____complete-keys"));
+
+        let zsh = generate(cli::build(), Shell::Zsh);
+        assert!(zsh.starts_with("#compdef dit\n\n__dit_keys() {"));
+        assert!(zsh.contains("\n(work-on|w)\n"));
+        assert!(zsh.contains(
+            "'::task -- The target task for the command. If absent, you'\\''ll be prompted to \
+             pick one of the recent tasks.:__dit_keys' \\"
+        ));
+        assert!(!zsh.contains("\"__complete-keys:"));
+
+        let fish = generate(cli::build(), Shell::Fish);
+        assert!(fish.contains(
+            "complete -c dit -n \"__fish_seen_subcommand_from work-on w\" -f -a \"(__dit_keys)\""
+        ));
+        assert!(!fish.contains("from __complete-keys"));
+    }
+}
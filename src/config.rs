@@ -0,0 +1,391 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use log::{debug, trace};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::time::{at, start_of_day, Timestamp};
+
+const CONFIG_FILE_NAME: &str = ".config.toml";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub schedule: Schedule,
+    pub timewarrior: Timewarrior,
+    pub ical: Ical,
+    pub csv: Csv,
+    pub git: Git,
+    /// Named templates for `--template` in `list` and `status`, and `--format` in `prompt`.
+    pub templates: HashMap<String, String>,
+}
+
+/// Expected working time for each day of the week.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Schedule {
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub monday: Option<Duration>,
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub tuesday: Option<Duration>,
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub wednesday: Option<Duration>,
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub thursday: Option<Duration>,
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub friday: Option<Duration>,
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub saturday: Option<Duration>,
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub sunday: Option<Duration>,
+    /// File listing the days off, one `YYYY-MM-DD` date per line. Relative paths are resolved
+    /// against the dit data directory.
+    pub holidays: Option<PathBuf>,
+    /// Working hours on the days with a target, e.g. `["08:00-12:00", "13:00-17:00"]`.
+    pub hours: Vec<Hours>,
+    /// Entries are not expected to last longer; open ones that do were most likely forgotten.
+    #[serde(with = "crate::utils::time::duration")]
+    pub max_entry: Duration,
+}
+
+/// A stretch of working hours within a day, written as `HH:MM-HH:MM`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Hours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Timewarrior {
+    /// Rules mapping Timewarrior tags to task keys, tried in order.
+    pub rules: Vec<TagRule>,
+}
+
+/// Maps tags matching the `tag` regex to the `task` key, which may refer to capture groups,
+/// e.g. `tag = "acme-(.*)"` and `task = "clients/acme/$1"`.
+#[derive(Deserialize)]
+pub struct TagRule {
+    pub tag: String,
+    pub task: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Ical {
+    /// Rules mapping calendar events to task keys, tried in order.
+    pub rules: Vec<EventRule>,
+}
+
+/// Maps events whose summary matches the `summary` regex to the `task` key, which may refer to
+/// capture groups.
+#[derive(Deserialize)]
+pub struct EventRule {
+    pub summary: String,
+    pub task: String,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Git {
+    /// Keeps the data directory in its own git repository, committing after every change.
+    pub enabled: bool,
+    /// The remote that `dit sync` merges with and pushes to.
+    pub remote: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Csv {
+    /// Named column mappings, usable alongside the built-in `toggl` and `clockify` presets.
+    pub mappings: HashMap<String, CsvMapping>,
+}
+
+/// Names the CSV columns holding each piece of a log entry. Without a time column, the date
+/// column holds both, e.g. `2026-10-16 09:00:00`.
+#[derive(Deserialize, Clone)]
+pub struct CsvMapping {
+    /// All columns written on export, in order, including ones dit leaves empty. Defaults to the
+    /// mapped ones.
+    #[serde(default)]
+    pub columns: Vec<String>,
+    pub project: Option<String>,
+    pub description: Option<String>,
+    /// Holds the entry notes; without it, they go in the description.
+    pub note: Option<String>,
+    pub start_date: Option<String>,
+    pub start_time: Option<String>,
+    pub end_date: Option<String>,
+    pub end_time: Option<String>,
+    pub duration: Option<String>,
+    pub tags: Option<String>,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+fn default_time_format() -> String {
+    "%H:%M:%S".to_string()
+}
+
+impl Config {
+    pub fn load(directory: &Path) -> Result<Self> {
+        let path = directory.join(CONFIG_FILE_NAME);
+
+        if !path.is_file() {
+            debug!("Config not found; using defaults");
+            return Ok(Config::default());
+        }
+
+        trace!("Reading config from: {}", path.display());
+
+        let s = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read file: {}", path.display()))?;
+        let mut config: Config = toml::from_str(s.as_str())
+            .with_context(|| format!("Could not parse file: {}", path.display()))?;
+
+        if let Some(h) = config.schedule.holidays.as_mut() {
+            if h.is_relative() {
+                *h = directory.join(&h);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns the template with the given name, unless it is a template already, i.e. it has a
+    /// placeholder.
+    pub fn template<'a>(&'a self, name: &'a str) -> Result<&'a str> {
+        if name.contains('{') {
+            return Ok(name);
+        }
+        self.templates
+            .get(name)
+            .map(String::as_str)
+            .with_context(|| {
+                format!(
+                    "No template named '{}' under '[templates]' in the configuration",
+                    name
+                )
+            })
+    }
+}
+
+impl Schedule {
+    pub fn target(&self, day: Weekday) -> Duration {
+        let t = match day {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        };
+        t.unwrap_or_else(Duration::zero)
+    }
+
+    /// The part of the target of `day` due by `until`: as much as the share of its working hours
+    /// passed, or all of it once the day is over if it has none. Nothing is due on holidays.
+    pub fn target_until(
+        &self,
+        day: NaiveDate,
+        until: Timestamp,
+        holidays: &HashSet<NaiveDate>,
+    ) -> Duration {
+        if holidays.contains(&day) {
+            return Duration::zero();
+        }
+        let target = self.target(day.weekday());
+
+        let hours = self.hours_on(day, holidays);
+        let total = hours
+            .iter()
+            .fold(Duration::zero(), |a, (start, end)| a + (*end - *start));
+        if total <= Duration::zero() {
+            return match until >= start_of_day(day) + Duration::days(1) {
+                true => target,
+                false => Duration::zero(),
+            };
+        }
+
+        let passed = hours.iter().fold(Duration::zero(), |a, (start, end)| {
+            a + (until.min(*end) - *start).max(Duration::zero())
+        });
+        Duration::seconds(target.num_seconds() * passed.num_seconds() / total.num_seconds())
+    }
+
+    /// The working hours of the given day, none on days off.
+    pub fn hours_on(
+        &self,
+        day: NaiveDate,
+        holidays: &HashSet<NaiveDate>,
+    ) -> Vec<(Timestamp, Timestamp)> {
+        if holidays.contains(&day) || self.target(day.weekday()) == Duration::zero() {
+            return Vec::new();
+        }
+        self.hours
+            .iter()
+            .map(|h| (at(day, h.start), at(day, h.end)))
+            .collect()
+    }
+
+    /// Whether an entry from `start` to `end` is too long to be right, e.g. after leaving the timer
+    /// running overnight.
+    pub fn is_too_long(&self, start: Timestamp, end: Timestamp) -> bool {
+        end - start > self.max_entry
+    }
+
+    /// Where an entry left running since `start` most likely ended: at the end of the working
+    /// hours of that day, but no later than the maximum entry length allows.
+    pub fn suggest_end(&self, start: Timestamp) -> Timestamp {
+        let latest = start + self.max_entry;
+        let day = start.naive_local().date();
+
+        self.hours
+            .iter()
+            .map(|h| at(day, h.end))
+            .filter(|e| *e > start)
+            .max()
+            .map_or(latest, |e| e.min(latest))
+    }
+
+    pub fn holidays(&self) -> Result<HashSet<NaiveDate>> {
+        match &self.holidays {
+            Some(p) => {
+                let s = fs::read_to_string(p)
+                    .with_context(|| format!("Could not read holidays file: {}", p.display()))?;
+                parse_holidays(&s)
+                    .with_context(|| format!("Could not parse holidays file: {}", p.display()))
+            }
+            None => Ok(HashSet::new()),
+        }
+    }
+}
+
+impl TryFrom<String> for Hours {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let time = |x: &str| {
+            NaiveTime::parse_from_str(x.trim(), "%H:%M")
+                .map_err(|_| format!("Invalid working hours: {}", s))
+        };
+        let (start, end) = match s.split_once('-') {
+            Some((a, b)) => (time(a)?, time(b)?),
+            None => {
+                return Err(format!(
+                    "Invalid working hours, expected HH:MM-HH:MM: {}",
+                    s
+                ))
+            }
+        };
+        if start >= end {
+            return Err(format!("Working hours end before they start: {}", s));
+        }
+        Ok(Hours { start, end })
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        let day = Some(Duration::hours(8));
+
+        Schedule {
+            monday: day,
+            tuesday: day,
+            wednesday: day,
+            thursday: day,
+            friday: day,
+            saturday: None,
+            sunday: None,
+            holidays: None,
+            hours: vec![
+                Hours::try_from("08:00-12:00".to_string()).unwrap(),
+                Hours::try_from("13:00-17:00".to_string()).unwrap(),
+            ],
+            max_entry: Duration::hours(10),
+        }
+    }
+}
+
+/// Parses one date per line; anything after the date is a description, and lines starting with
+/// '#' are comments.
+fn parse_holidays(s: &str) -> Result<HashSet<NaiveDate>> {
+    s.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            let d = l.split_whitespace().next().unwrap();
+            NaiveDate::parse_from_str(d, "%F").with_context(|| format!("Invalid date: {}", d))
+        })
+        .collect()
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        Git {
+            enabled: false,
+            remote: "origin".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{Duration, NaiveDate, NaiveTime};
+    use std::convert::TryFrom;
+
+    use super::{parse_holidays, Hours, Schedule};
+    use crate::utils::time::parse_timestamp;
+
+    #[test]
+    fn test_parse_holidays() {
+        let h = parse_holidays("# Holidays\n\n2026-12-25 Christmas\n2026-01-01\n").unwrap();
+
+        assert_eq!(h.len(), 2);
+        assert!(h.contains(&NaiveDate::from_ymd_opt(2026, 12, 25).unwrap()));
+        assert!(h.contains(&NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()));
+
+        assert!(parse_holidays("2026-13-01").is_err());
+    }
+    #[test]
+    fn test_hours() {
+        let h = Hours::try_from("8:30-12:00".to_string()).unwrap();
+
+        assert_eq!(h.start, NaiveTime::from_hms_opt(8, 30, 0).unwrap());
+        assert_eq!(h.end, NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+
+        assert!(Hours::try_from("12:00-8:30".to_string()).is_err());
+        assert!(Hours::try_from("8:30".to_string()).is_err());
+        assert!(Hours::try_from("8:30-25:00".to_string()).is_err());
+    }
+    #[test]
+    fn test_suggest_end() {
+        let schedule = Schedule::default();
+        let t = |x| parse_timestamp(x).unwrap();
+
+        assert!(schedule.is_too_long(t("2021-03-01-09:00"), t("2021-03-02-01:00")));
+        assert!(!schedule.is_too_long(t("2021-03-01-09:00"), t("2021-03-01-18:00")));
+
+        // The end of the working day
+        assert_eq!(
+            schedule.suggest_end(t("2021-03-01-09:00")),
+            t("2021-03-01-17:00")
+        );
+        // Started after the working hours
+        assert_eq!(
+            schedule.suggest_end(t("2021-03-01-20:00")),
+            t("2021-03-01-20:00") + Duration::hours(10)
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Reads and writes CSV reports laid out like those of Toggl and Clockify.
+//!
+//! Which column holds what is described by a `CsvMapping`; presets for `toggl` and `clockify` are
+//! built in, and more can be defined in the configuration. The project column holds the task key,
+//! or else the tags column does.
+//! Times are read in, and written out as, local time.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use std::collections::HashMap;
+
+use crate::config::CsvMapping;
+use crate::formats::Imported;
+use crate::models::{ListItem, LogEntry, Task};
+use crate::utils::time::{to_local, Timestamp};
+
+pub fn preset(name: &str) -> Option<CsvMapping> {
+    match name {
+        "toggl" => Some(CsvMapping {
+            columns: to_strings(&[
+                "User",
+                "Email",
+                "Client",
+                "Project",
+                "Task",
+                "Description",
+                "Billable",
+                "Start date",
+                "Start time",
+                "End date",
+                "End time",
+                "Duration",
+                "Tags",
+                "Amount ()",
+            ]),
+            project: Some("Project".to_string()),
+            description: Some("Description".to_string()),
+            note: None,
+            start_date: Some("Start date".to_string()),
+            start_time: Some("Start time".to_string()),
+            end_date: Some("End date".to_string()),
+            end_time: Some("End time".to_string()),
+            duration: Some("Duration".to_string()),
+            tags: Some("Tags".to_string()),
+            date_format: "%Y-%m-%d".to_string(),
+            time_format: "%H:%M:%S".to_string(),
+        }),
+        "clockify" => Some(CsvMapping {
+            columns: to_strings(&[
+                "Project",
+                "Client",
+                "Description",
+                "Task",
+                "User",
+                "Group",
+                "Email",
+                "Tags",
+                "Billable",
+                "Start Date",
+                "Start Time",
+                "End Date",
+                "End Time",
+                "Duration (h)",
+                "Duration (decimal)",
+            ]),
+            project: Some("Project".to_string()),
+            description: Some("Description".to_string()),
+            note: None,
+            start_date: Some("Start Date".to_string()),
+            start_time: Some("Start Time".to_string()),
+            end_date: Some("End Date".to_string()),
+            end_time: Some("End Time".to_string()),
+            duration: Some("Duration (h)".to_string()),
+            tags: Some("Tags".to_string()),
+            date_format: "%m/%d/%Y".to_string(),
+            time_format: "%I:%M:%S %p".to_string(),
+        }),
+        _ => None,
+    }
+}
+
+fn to_strings(x: &[&str]) -> Vec<String> {
+    x.iter().map(|s| s.to_string()).collect()
+}
+
+/// The columns written on export: the ones listed in the mapping, or else the mapped ones.
+fn header(mapping: &CsvMapping) -> Vec<String> {
+    if !mapping.columns.is_empty() {
+        return mapping.columns.clone();
+    }
+
+    let mapped = [
+        &mapping.project,
+        &mapping.description,
+        &mapping.note,
+        &mapping.start_date,
+        &mapping.start_time,
+        &mapping.end_date,
+        &mapping.end_time,
+        &mapping.duration,
+        &mapping.tags,
+    ];
+
+    let mut columns: Vec<String> = Vec::new();
+    for c in mapped.iter().filter_map(|c| c.as_ref()) {
+        if !columns.contains(c) {
+            columns.push(c.clone());
+        }
+    }
+    columns
+}
+
+/// Writes the entries in chronological order. Without a note column, the description is the
+/// entry's note, or else the task's title; without a project column, the task key is the tag.
+pub fn export(items: &[ListItem], mapping: &CsvMapping) -> Result<String> {
+    let header = header(mapping);
+
+    let mut w = ::csv::Writer::from_writer(Vec::new());
+    w.write_record(&header)?;
+
+    for x in items.iter().rev() {
+        let (start_date, start_time) = format_split(&x.start(), &mapping.start_time, mapping);
+        let (end_date, end_time) = match x.end() {
+            Some(end) => format_split(&end, &mapping.end_time, mapping),
+            None => (String::new(), String::new()),
+        };
+
+        let (description, note) = match mapping.note {
+            Some(_) => (x.title.clone(), x.log_entry.note.clone().unwrap_or_default()),
+            None => (
+                x.log_entry.note.clone().unwrap_or_else(|| x.title.clone()),
+                String::new(),
+            ),
+        };
+
+        let tags = match mapping.project {
+            Some(_) => String::new(),
+            None => x.id.clone(),
+        };
+
+        let fields = vec![
+            (&mapping.project, x.id.clone()),
+            (&mapping.description, description),
+            (&mapping.note, note),
+            (&mapping.start_date, start_date),
+            (&mapping.start_time, start_time),
+            (&mapping.end_date, end_date),
+            (&mapping.end_time, end_time),
+            (&mapping.duration, format_hms(&x.effort())),
+            (&mapping.tags, tags),
+        ];
+
+        let mut values: HashMap<&str, String> = HashMap::new();
+        for (column, value) in fields {
+            if let Some(c) = column {
+                values.entry(c.as_str()).or_insert(value);
+            }
+        }
+
+        let row: Vec<&str> = header
+            .iter()
+            .map(|c| values.get(c.as_str()).map(|v| v.as_str()).unwrap_or(""))
+            .collect();
+        w.write_record(&row)?;
+    }
+
+    let out = w.into_inner().context("Could not write CSV")?;
+    String::from_utf8(out).context("Could not write CSV")
+}
+
+/// Formats the date and time for their columns; without a time column, both go in the date one.
+fn format_split(x: &Timestamp, time: &Option<String>, mapping: &CsvMapping) -> (String, String) {
+    let x = x.with_timezone(&Local);
+
+    match time {
+        Some(_) => (
+            x.format(&mapping.date_format).to_string(),
+            x.format(&mapping.time_format).to_string(),
+        ),
+        None => (
+            format!(
+                "{} {}",
+                x.format(&mapping.date_format),
+                x.format(&mapping.time_format)
+            ),
+            String::new(),
+        ),
+    }
+}
+
+/// Formats as `HH:MM:SS`, which both Toggl and Clockify use.
+fn format_hms(x: &Duration) -> String {
+    let s = x.num_seconds();
+    format!("{:02}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+}
+
+fn parse_hms(x: &str) -> Option<Duration> {
+    let parts = x
+        .trim()
+        .split(':')
+        .map(|p| p.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match parts.as_slice() {
+        [h, m] => Some(Duration::hours(*h) + Duration::minutes(*m)),
+        [h, m, s] => Some(Duration::hours(*h) + Duration::minutes(*m) + Duration::seconds(*s)),
+        _ => None,
+    }
+}
+
+struct Columns {
+    project: Option<usize>,
+    description: Option<usize>,
+    note: Option<usize>,
+    start_date: usize,
+    start_time: Option<usize>,
+    end_date: Option<usize>,
+    end_time: Option<usize>,
+    duration: Option<usize>,
+    tags: Option<usize>,
+}
+
+fn find_columns(headers: &::csv::StringRecord, mapping: &CsvMapping) -> Result<Columns> {
+    let find = |column: &Option<String>| -> Result<Option<usize>> {
+        match column {
+            Some(c) => match headers.iter().position(|h| h.trim() == c) {
+                Some(i) => Ok(Some(i)),
+                None => bail!("Column not found: {}", c),
+            },
+            None => Ok(None),
+        }
+    };
+
+    let columns = Columns {
+        project: find(&mapping.project)?,
+        description: find(&mapping.description)?,
+        note: find(&mapping.note)?,
+        start_date: match find(&mapping.start_date)? {
+            Some(i) => i,
+            None => bail!("The mapping has no start date column"),
+        },
+        start_time: find(&mapping.start_time)?,
+        end_date: find(&mapping.end_date)?,
+        end_time: find(&mapping.end_time)?,
+        duration: find(&mapping.duration)?,
+        tags: find(&mapping.tags)?,
+    };
+
+    if columns.project.is_none() && columns.tags.is_none() {
+        bail!("The mapping has neither a project nor a tags column");
+    }
+    Ok(columns)
+}
+
+/// Reads the rows of a report, describing the ones that could not be converted in `problems`.
+/// The task key is the project, or else the first tag that is a valid key. The description
+/// becomes the title of tasks that do not exist yet and, without a note column, the entry's note.
+pub fn parse(s: &str, mapping: &CsvMapping, problems: &mut Vec<String>) -> Result<Vec<Imported>> {
+    let mut r = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(s.trim_start_matches('\u{feff}').as_bytes());
+
+    let columns = find_columns(r.headers()?, mapping)?;
+
+    let mut imported = Vec::new();
+
+    for (n, record) in r.records().enumerate() {
+        let line = n + 2;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                problems.push(format!("line {}: {}", line, e));
+                continue;
+            }
+        };
+        let get = |i: Option<usize>| {
+            i.and_then(|i| record.get(i))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
+
+        let id = match find_key(get(columns.project), get(columns.tags)) {
+            Some(id) => id,
+            None => {
+                problems.push(format!("line {}: no project or tag is a task key", line));
+                continue;
+            }
+        };
+
+        let title = get(columns.description).map(|d| d.to_string());
+        let note = match columns.note {
+            Some(_) => get(columns.note).map(|n| n.to_string()),
+            None => title.clone(),
+        };
+
+        match convert_times(&columns, mapping, get) {
+            Ok(entry) => imported.push(Imported {
+                id,
+                title,
+                entry: LogEntry { note, ..entry },
+            }),
+            Err(e) => problems.push(format!("line {}: {:#}", line, e)),
+        }
+    }
+    Ok(imported)
+}
+
+fn find_key(project: Option<&str>, tags: Option<&str>) -> Option<String> {
+    project
+        .into_iter()
+        .chain(
+            tags.into_iter()
+                .flat_map(|t| t.split(','))
+                .map(|t| t.trim()),
+        )
+        .find(|k| Task::validate_key(k).is_ok())
+        .map(|k| k.to_string())
+}
+
+fn convert_times<'a, F>(columns: &Columns, mapping: &CsvMapping, get: F) -> Result<LogEntry>
+where
+    F: Fn(Option<usize>) -> Option<&'a str>,
+{
+    let start = match get(Some(columns.start_date)) {
+        Some(d) => parse_split(d, get(columns.start_time), mapping)?,
+        None => bail!("No start date"),
+    };
+
+    let end = match (get(columns.end_date), get(columns.duration)) {
+        (Some(d), _) => Some(parse_split(d, get(columns.end_time), mapping)?),
+        (None, Some(x)) => match parse_hms(x) {
+            Some(d) => Some(start + d),
+            None => bail!("Invalid duration: {}", x),
+        },
+        (None, None) => None,
+    };
+
+    if let Some(end) = end {
+        if end < start {
+            bail!("Ends before it starts");
+        }
+    }
+
+    Ok(LogEntry {
+        start,
+        end,
+        note: None,
+    })
+}
+
+/// Parses the date and time columns; without a time column, the date one holds both.
+fn parse_split(date: &str, time: Option<&str>, mapping: &CsvMapping) -> Result<Timestamp> {
+    let naive = match time {
+        Some(time) => NaiveDate::parse_from_str(date, &mapping.date_format)
+            .with_context(|| format!("Invalid date: {}", date))?
+            .and_time(
+                NaiveTime::parse_from_str(time, &mapping.time_format)
+                    .with_context(|| format!("Invalid time: {}", time))?,
+            ),
+        None => NaiveDateTime::parse_from_str(
+            date,
+            &format!("{} {}", mapping.date_format, mapping.time_format),
+        )
+        .with_context(|| format!("Invalid date/time: {}", date))?,
+    };
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| to_local(t.with_timezone(&Utc)))
+        .with_context(|| format!("Invalid local time: {}", naive))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::Duration;
+
+    use super::{export, find_key, format_hms, parse, parse_hms, preset};
+    use crate::config::CsvMapping;
+    use crate::models::list_item;
+
+    #[test]
+    fn test_parse() {
+        let s = "\
+User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags,Amount ()
+me,me@example.com,,clients/acme/api,,The API,No,2026-10-16,09:00:00,2026-10-16,10:30:00,01:30:00,,
+me,me@example.com,,Acme Website,,Styling,No,2026-10-16,11:00:00,,,00:45:00,\"urgent, acme\",
+me,me@example.com,,Acme Website,,Styling,No,2026-10-16,13:00:00,,,00:45:00,,
+me,me@example.com,,meetings,,Standup,No,16/10/2026,09:00:00,,,00:15:00,,
+";
+        let mut problems = Vec::new();
+        let imported = parse(s, &preset("toggl").unwrap(), &mut problems).unwrap();
+
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].id, "clients/acme/api");
+        assert_eq!(imported[0].title.as_deref(), Some("The API"));
+        assert_eq!(imported[0].entry.note.as_deref(), Some("The API"));
+        assert_eq!(imported[0].entry.effort(), Duration::minutes(90));
+        assert_eq!(imported[1].id, "urgent");
+        assert_eq!(imported[1].entry.effort(), Duration::minutes(45));
+        assert_eq!(problems.len(), 2);
+
+        let s = "Project,Start Date\nfoo,10/16/2026\n";
+        assert!(parse(s, &preset("toggl").unwrap(), &mut problems).is_err());
+    }
+
+    #[test]
+    fn test_export_by_tags() {
+        let mapping = CsvMapping {
+            columns: Vec::new(),
+            project: None,
+            description: Some("Description".to_string()),
+            note: None,
+            start_date: Some("Start".to_string()),
+            start_time: Some("Start time".to_string()),
+            end_date: Some("End".to_string()),
+            end_time: None,
+            duration: None,
+            tags: Some("Tags".to_string()),
+            date_format: "%Y-%m-%d".to_string(),
+            time_format: "%H:%M".to_string(),
+        };
+        let items = vec![list_item(
+            "acme/api",
+            "2021-03-01-09:00",
+            Some("2021-03-01-10:30"),
+        )];
+
+        let s = export(&items, &mapping).unwrap();
+        assert_eq!(
+            s.lines().nth(1),
+            Some(",2021-03-01,09:00,2021-03-01 10:30,acme/api")
+        );
+
+        let mut problems = Vec::new();
+        let imported = parse(&s, &mapping, &mut problems).unwrap();
+        assert!(problems.is_empty());
+        assert_eq!(imported[0].id, "acme/api");
+        assert_eq!(imported[0].entry.end, items[0].end());
+    }
+
+    #[test]
+    fn test_hms() {
+        assert_eq!(format_hms(&Duration::minutes(90)), "01:30:00");
+        assert_eq!(format_hms(&Duration::hours(30)), "30:00:00");
+        assert_eq!(parse_hms("01:30:15"), Some(Duration::seconds(5415)));
+        assert_eq!(parse_hms("1:30"), Some(Duration::minutes(90)));
+        assert_eq!(parse_hms("1.5"), None);
+    }
+
+    #[test]
+    fn test_find_key() {
+        assert_eq!(find_key(Some("foo/bar"), None), Some("foo/bar".to_string()));
+        assert_eq!(
+            find_key(Some("Foo Bar"), Some("a b, baz")),
+            Some("baz".to_string())
+        );
+        assert_eq!(find_key(None, None), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! Reads the data directories of dit version 1.
+//!
+//! Version 1 stores one JSON file per task, at `<group>/<subgroup>/<task>`, where '_' stands for
+//! an empty group or subgroup. Each file has a `title` and a `logbook` of `in`/`out` entries.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Timelike};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::models::{LogEntry, Task};
+use crate::utils::directory::is_hidden;
+use crate::utils::time::Timestamp;
+
+const EMPTY_NAME: &str = "_";
+
+#[derive(Deserialize)]
+struct V1Task {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    logbook: Vec<V1Entry>,
+}
+
+#[derive(Deserialize)]
+struct V1Entry {
+    #[serde(rename = "in")]
+    clock_in: Option<String>,
+    #[serde(rename = "out")]
+    clock_out: Option<String>,
+}
+
+/// The tasks that could be converted, and descriptions of whatever could not.
+pub struct Conversion {
+    pub tasks: Vec<Task>,
+    pub problems: Vec<String>,
+}
+
+pub fn read(directory: &Path) -> Result<Conversion> {
+    let mut conversion = Conversion {
+        tasks: Vec::new(),
+        problems: Vec::new(),
+    };
+
+    // Skips v1's own files, e.g. '.index' and '.current', and hidden directories such as '.git'
+    let walk = WalkDir::new(directory)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_hidden(e.file_name()));
+
+    for entry in walk {
+        let entry = entry
+            .with_context(|| format!("Could not complete traversal of: {}", directory.display()))?;
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let rel = path.strip_prefix(directory).unwrap();
+
+        let id = match to_key(rel) {
+            Some(id) => id,
+            None => {
+                conversion
+                    .problems
+                    .push(format!("{}: not a valid task key", rel.display()));
+                continue;
+            }
+        };
+
+        match convert(&id, path, &mut conversion.problems) {
+            Ok(task) => conversion.tasks.push(task),
+            Err(e) => conversion
+                .problems
+                .push(format!("{}: {:#}", rel.display(), e)),
+        }
+    }
+    Ok(conversion)
+}
+
+fn convert(id: &str, path: &Path, problems: &mut Vec<String>) -> Result<Task> {
+    let s = fs::read_to_string(path)
+        .with_context(|| format!("Could not read file: {}", path.display()))?;
+    let v1: V1Task = serde_json::from_str(&s).context("Could not parse v1 task")?;
+
+    let mut task = Task::new(id.to_string());
+
+    task.data.title = match v1.title {
+        Some(t) if !t.trim().is_empty() => t.trim().to_string(),
+        _ => id.rsplit('/').next().unwrap().to_string(),
+    };
+
+    for (i, e) in v1.logbook.iter().enumerate() {
+        match convert_entry(e) {
+            Ok(entry) => task.data.log.push(entry),
+            Err(err) => problems.push(format!("{}: entry {}: {:#}", id, i, err)),
+        }
+    }
+    task.data.log.sort();
+
+    Ok(task)
+}
+
+fn convert_entry(e: &V1Entry) -> Result<LogEntry> {
+    let start = match &e.clock_in {
+        Some(x) => parse_v1_timestamp(x)?,
+        None => bail!("Missing clock-in time"),
+    };
+    let end = match &e.clock_out {
+        Some(x) => Some(parse_v1_timestamp(x)?),
+        None => None,
+    };
+
+    if end.map(|x| x < start).unwrap_or(false) {
+        bail!("Clock-out is before clock-in");
+    }
+    Ok(LogEntry {
+        start,
+        end,
+        note: None,
+    })
+}
+
+/// Maps `group/subgroup/task` to a v2 key, dropping the '_' placeholders.
+fn to_key(rel: &Path) -> Option<String> {
+    let s = rel.with_extension("").display().to_string();
+
+    let key = s
+        .split('/')
+        .filter(|x| *x != EMPTY_NAME)
+        .collect::<Vec<_>>()
+        .join("/");
+
+    match Task::validate_key(&key) {
+        Ok(()) => Some(key),
+        Err(_) => None,
+    }
+}
+
+fn parse_v1_timestamp(x: &str) -> Result<Timestamp> {
+    DateTime::parse_from_rfc3339(x)
+        .or_else(|_| DateTime::parse_from_str(x, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .or_else(|_| DateTime::parse_from_str(x, "%Y-%m-%d %H:%M:%S%.f%z"))
+        .map(|t| t.with_nanosecond(0).unwrap())
+        .with_context(|| format!("Invalid timestamp: {}", x))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::fs;
+
+    use super::{convert_entry, read, to_key, V1Entry};
+    use crate::utils::testing::TempDir;
+
+    #[test]
+    fn test_to_key() {
+        assert_eq!(
+            to_key(Path::new("foo/bar/baz")),
+            Some("foo/bar/baz".to_string())
+        );
+        assert_eq!(to_key(Path::new("_/_/baz")), Some("baz".to_string()));
+        assert_eq!(
+            to_key(Path::new("foo/_/baz.json")),
+            Some("foo/baz".to_string())
+        );
+        assert_eq!(to_key(Path::new("foo/bar/1baz")), None);
+    }
+
+    #[test]
+    fn test_convert_entry() {
+        let e = convert_entry(&V1Entry {
+            clock_in: Some("2017-07-25T14:18:07.123-03:00".to_string()),
+            clock_out: Some("2017-07-25T14:50:12-03:00".to_string()),
+        })
+        .unwrap();
+
+        assert_eq!(e.start.to_rfc3339(), "2017-07-25T14:18:07-03:00");
+        assert_eq!(e.effort().num_seconds(), 32 * 60 + 5);
+
+        assert!(convert_entry(&V1Entry {
+            clock_in: Some("2017-07-25T14:18:07-03:00".to_string()),
+            clock_out: Some("2017-07-25T13:18:07-03:00".to_string()),
+        })
+        .is_err());
+
+        assert!(convert_entry(&V1Entry {
+            clock_in: None,
+            clock_out: None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_read() {
+        let tmp = TempDir::new("v1");
+        let dir = tmp.path().to_path_buf();
+        fs::create_dir_all(dir.join("acme/_")).unwrap();
+        fs::create_dir_all(dir.join(".git/refs")).unwrap();
+
+        let task = r#"{"title": "API", "logbook": []}"#;
+        fs::write(dir.join("acme/_/api"), task).unwrap();
+        fs::write(dir.join(".git/refs/main"), "not a task").unwrap();
+        fs::write(dir.join(".index"), "not a task").unwrap();
+
+        let conversion = read(&dir).unwrap();
+        assert_eq!(conversion.tasks.len(), 1);
+        assert_eq!(conversion.tasks[0].id, "acme/api");
+        assert!(conversion.problems.is_empty());
+    }
+}
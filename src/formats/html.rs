@@ -0,0 +1,205 @@
+//! Self-contained HTML pages, e.g. to be sent by email: styles are inline and charts are SVG, so
+//! nothing is fetched when the page is opened.
+
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+use crate::models::ListItem;
+use crate::utils::nice::Nice;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1, h2, h3 { font-weight: normal; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.3em 0.8em; text-align: left; }
+th { background: #f4f4f4; }
+.num { text-align: right; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.3em 1em; }
+dt { color: #666; }
+dd { margin: 0; }
+svg text { font-size: 12px; }";
+
+const BAR_HEIGHT: usize = 18;
+const LABEL_WIDTH: usize = 220;
+const CHART_WIDTH: usize = 400;
+
+/// Escapes text for use in elements and attribute values.
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n{style}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape(title),
+        style = STYLE,
+        body = body,
+    )
+}
+
+/// Horizontal bars, one per label, scaled to the longest one.
+pub fn bar_chart(bars: &[(String, Duration)]) -> String {
+    let max = bars
+        .iter()
+        .map(|(_, d)| d.num_seconds())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        LABEL_WIDTH + CHART_WIDTH + 80,
+        bars.len() * BAR_HEIGHT + 4
+    );
+    for (i, (label, d)) in bars.iter().enumerate() {
+        let y = i * BAR_HEIGHT;
+        let w = d.num_seconds().max(0) as usize * CHART_WIDTH / max as usize;
+        svg.push_str(&format!(
+            "<text x=\"{lx}\" y=\"{ty}\" text-anchor=\"end\">{label}</text>\
+             <rect x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\" fill=\"#4a90d9\"/>\
+             <text x=\"{vx}\" y=\"{ty}\">{value}</text>\n",
+            lx = LABEL_WIDTH - 6,
+            ty = y + BAR_HEIGHT - 5,
+            label = escape(label),
+            x = LABEL_WIDTH,
+            y = y + 2,
+            w = w,
+            h = BAR_HEIGHT - 4,
+            vx = LABEL_WIDTH + w + 4,
+            value = d.nice(),
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A report of the given entries, which come most recent first as listed: a summary, charts of
+/// the effort per day, oldest first, and per task, largest first, then the entries of each task.
+pub fn report(items: &[ListItem]) -> String {
+    let total = items.iter().fold(Duration::zero(), |a, x| a + x.effort());
+
+    let mut days: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+    let mut tasks: BTreeMap<&str, Vec<&ListItem>> = BTreeMap::new();
+    for x in items {
+        let d = days
+            .entry(x.start().naive_local().date())
+            .or_insert_with(Duration::zero);
+        *d += x.effort();
+        tasks.entry(&x.id).or_default().push(x);
+    }
+
+    let mut per_task: Vec<(&str, Duration)> = tasks
+        .iter()
+        .map(|(id, xs)| (*id, xs.iter().fold(Duration::zero(), |a, x| a + x.effort())))
+        .collect();
+    per_task.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(y.0)));
+
+    let mut body = String::new();
+
+    if let (Some(last), Some(first)) = (items.first(), items.last()) {
+        body.push_str(&format!(
+            "<p>From {} to {}.</p>\n",
+            first.start().format("%A %F"),
+            last.start().format("%A %F")
+        ));
+    }
+    body.push_str(&format!(
+        "<dl>\n<dt>Tracked</dt><dd>{}</dd>\n<dt>Entries</dt><dd>{}</dd>\n\
+         <dt>Days</dt><dd>{}</dd>\n<dt>Tasks</dt><dd>{}</dd>\n</dl>\n",
+        total.nice(),
+        items.len(),
+        days.len(),
+        tasks.len()
+    ));
+
+    body.push_str("<h2>Per day</h2>\n");
+    body.push_str(&bar_chart(
+        &days
+            .into_iter()
+            .map(|(day, d)| (day.format("%a %F").to_string(), d))
+            .collect::<Vec<_>>(),
+    ));
+
+    body.push_str("<h2>Per task</h2>\n");
+    body.push_str(&bar_chart(
+        &per_task
+            .iter()
+            .map(|(id, d)| (id.to_string(), *d))
+            .collect::<Vec<_>>(),
+    ));
+
+    for (id, effort) in per_task {
+        let entries = &tasks[id];
+        body.push_str(&format!(
+            "<h3>{} &mdash; {}</h3>\n<table>\n<thead><tr><th>Start</th><th>End</th>\
+             <th class=\"num\">Effort</th><th>Note</th></tr></thead>\n<tbody>\n",
+            escape(id),
+            escape(&entries[0].title)
+        ));
+        for x in entries {
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+                x.start().nice(),
+                x.end().nice(),
+                x.effort().nice(),
+                escape(x.log_entry.note.as_deref().unwrap_or_default())
+            ));
+        }
+        body.push_str(&format!(
+            "</tbody>\n<tfoot><tr><th colspan=\"2\">Total</th><th class=\"num\">{}</th>\
+             <th></th></tr></tfoot>\n</table>\n",
+            effort.nice()
+        ));
+    }
+
+    page("Time report", &body)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{bar_chart, escape, report};
+    use crate::models::{ListItem, LogEntry, Task};
+    use crate::utils::time::parse_timestamp;
+    use chrono::Duration;
+
+    #[test]
+    fn test_report() {
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+
+        let svg = bar_chart(&[
+            ("a".to_string(), Duration::hours(2)),
+            ("b".to_string(), Duration::hours(1)),
+        ]);
+        assert!(svg.contains("width=\"400\""));
+        assert!(svg.contains("width=\"200\""));
+
+        let mut task = Task::new("acme/api".to_string());
+        task.data.title = "API <v2>".to_string();
+        let entry = LogEntry {
+            start: parse_timestamp("2021-03-04-09:00").unwrap(),
+            end: parse_timestamp("2021-03-04-10:30"),
+            note: Some("Review".to_string()),
+        };
+        let html = report(&[ListItem::new(&task, &entry)]);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h3>acme/api &mdash; API &lt;v2&gt;</h3>"));
+        assert!(html.contains("<dt>Tracked</dt><dd>1h30min</dd>"));
+        assert!(html.contains("<td>Review</td>"));
+
+        // Days come in order of date, not of weekday name
+        let monday = LogEntry {
+            start: parse_timestamp("2021-03-08-09:00").unwrap(),
+            end: parse_timestamp("2021-03-08-10:00"),
+            note: None,
+        };
+        let html = report(&[ListItem::new(&task, &monday), ListItem::new(&task, &entry)]);
+        let thu = html.find("Thu 2021-03-04").unwrap();
+        assert!(thu < html.find("Mon 2021-03-08").unwrap());
+    }
+}
@@ -0,0 +1,943 @@
+//! Writes log entries as iCalendar (RFC 5545) events, and reads events back as log entries.
+//!
+//! Times with a `TZID` are converted with the `VTIMEZONE` of that name in the same file, as there
+//! is no timezone database at hand; its yearly changes must follow the usual `BYMONTH` and `BYDAY`
+//! rules. Events in a zone that is neither defined there nor the local one are reported instead.
+//! Recurring events are expanded for the simple daily and weekly rules calendars usually export.
+
+use anyhow::{bail, Context, Result};
+use chrono::{
+    Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+
+use crate::config::EventRule;
+use crate::formats::{apply_rule, rule_regex, Imported};
+use crate::models::{ListItem, LogEntry, Task};
+use crate::utils::nice::Nice;
+use crate::utils::time::{now, to_local, Timestamp};
+
+lazy_static! {
+    static ref ICAL_DURATION_RE: Regex = Regex::new(
+        r"^(?P<sign>[+-])?P((?P<w>\d+)W)?((?P<d>\d+)D)?(T((?P<h>\d+)H)?((?P<min>\d+)M)?((?P<s>\d+)S)?)?$"
+    )
+    .unwrap();
+}
+
+const ICAL_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const ICAL_LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Writes one VEVENT per closed entry. UIDs only depend on the task and the entry's start, so
+/// importing a newer export updates events instead of duplicating them.
+pub fn export(items: &[ListItem]) -> String {
+    let stamp = format_ical_timestamp(&now());
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//dit//dit {}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for x in items.iter().rev() {
+        let end = match x.end() {
+            Some(e) => e,
+            None => continue,
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", uid(&x.id, &x.start())));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", format_ical_timestamp(&x.start())));
+        lines.push(format!("DTEND:{}", format_ical_timestamp(&end)));
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape(&format!("{}: {}", x.id, x.title))
+        ));
+        if let Some(n) = &x.log_entry.note {
+            lines.push(format!("DESCRIPTION:{}", escape(n)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|l| fold(l)).collect()
+}
+
+pub fn uid(id: &str, start: &Timestamp) -> String {
+    format!(
+        "{}-{}@dit",
+        start.with_timezone(&Utc).format(ICAL_FORMAT),
+        id.trim_start_matches('/').replace('/', ".")
+    )
+}
+
+fn format_ical_timestamp(x: &Timestamp) -> String {
+    x.with_timezone(&Utc).format(ICAL_FORMAT).to_string()
+}
+
+fn escape(x: &str) -> String {
+    x.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits lines longer than 75 octets, and terminates them with CRLF.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+struct Property {
+    name: String,
+    params: HashMap<String, String>,
+    value: String,
+}
+
+/// A `VTIMEZONE`: the UTC offsets in use over time.
+#[derive(Clone)]
+struct Zone {
+    observances: Vec<Observance>,
+}
+
+/// A `STANDARD` or `DAYLIGHT` block of a `VTIMEZONE`.
+#[derive(Clone)]
+struct Observance {
+    /// When the offset first changed, in the wall time of the offset before.
+    start: NaiveDateTime,
+    offset_from: FixedOffset,
+    offset_to: FixedOffset,
+    /// The month and weekday it changes again every year, e.g. `(10, -1, Sun)` for the last
+    /// Sunday of October.
+    yearly: Option<(u32, i64, Weekday)>,
+    until: Option<NaiveDateTime>,
+}
+
+struct Event {
+    summary: String,
+    start: Timestamp,
+    /// The start as written, in the wall time of `zone`, or of the local zone if there is none.
+    wall_start: NaiveDateTime,
+    zone: Option<Zone>,
+    end: Timestamp,
+    rrule: Option<String>,
+    exdates: Vec<Timestamp>,
+    recurrence_id: Option<(String, Timestamp)>,
+    uid: Option<String>,
+}
+
+/// Converts the events that happened between `after` and `before` into log entries, describing
+/// the ones that could not be converted in `problems`.
+pub fn parse(
+    s: &str,
+    rules: &[EventRule],
+    after: Option<Timestamp>,
+    before: Timestamp,
+    problems: &mut Vec<String>,
+) -> Result<Vec<Imported>> {
+    let rules = rules
+        .iter()
+        .map(|r| Ok((rule_regex(&r.summary, false)?, r.task.as_str())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut blocks = Vec::new();
+    let mut zones = HashMap::new();
+    let mut current: Option<Vec<Property>> = None;
+    let mut zone: Option<(Vec<Property>, Vec<Vec<Property>>)> = None;
+
+    for line in unfold(s) {
+        match line.as_str() {
+            "BEGIN:VEVENT" | "BEGIN:STANDARD" | "BEGIN:DAYLIGHT" => current = Some(Vec::new()),
+            "END:VEVENT" => blocks.extend(current.take()),
+            "BEGIN:VTIMEZONE" => zone = Some((Vec::new(), Vec::new())),
+            "END:STANDARD" | "END:DAYLIGHT" => {
+                if let (Some(props), Some((_, observances))) = (current.take(), zone.as_mut()) {
+                    observances.push(props);
+                }
+            }
+            "END:VTIMEZONE" => {
+                if let Some((props, observances)) = zone.take() {
+                    let tzid = props.iter().find(|p| p.name == "TZID");
+                    match (tzid, to_zone(&observances)) {
+                        (Some(id), Ok(z)) => {
+                            zones.insert(id.value.clone(), z);
+                        }
+                        (Some(id), Err(e)) => problems.push(format!("{}: {:#}", id.value, e)),
+                        (None, _) => problems.push("Time zone without TZID".to_string()),
+                    }
+                }
+            }
+            _ => {
+                let props = match (current.as_mut(), zone.as_mut()) {
+                    (Some(props), _) => props,
+                    (None, Some((props, _))) => props,
+                    (None, None) => continue,
+                };
+                if let Some(p) = parse_property(&line) {
+                    props.push(p);
+                }
+            }
+        }
+    }
+
+    // Time zones may come after the events using them
+    let mut events = Vec::new();
+    for props in blocks {
+        match to_event(&props, &zones) {
+            Ok(e) => events.push(e),
+            Err(e) => problems.push(format!("{}: {:#}", describe(&props), e)),
+        }
+    }
+
+    // Occurrences that were moved or edited are events on their own, with a RECURRENCE-ID
+    let overridden: HashSet<(String, Timestamp)> = events
+        .iter()
+        .filter_map(|e| e.recurrence_id.clone())
+        .collect();
+
+    let mut imported = Vec::new();
+
+    for e in events.iter() {
+        let occurrences = match occurrences(e, before) {
+            Ok(o) => o,
+            Err(err) => {
+                problems.push(format!("{}: {:#}", e.summary, err));
+                continue;
+            }
+        };
+
+        for start in occurrences {
+            let is_overridden = e.recurrence_id.is_none()
+                && e.uid
+                    .as_ref()
+                    .map(|u| overridden.contains(&(u.clone(), start)))
+                    .unwrap_or(false);
+
+            if is_overridden || e.exdates.contains(&start) {
+                continue;
+            }
+            if after.map(|a| start < a).unwrap_or(false) || start > before {
+                continue;
+            }
+
+            let id = match rules
+                .iter()
+                .find_map(|(re, task)| apply_rule(re, task, &e.summary))
+            {
+                Some(id) => id,
+                None => {
+                    problems.push(format!("{} {}: no rule matches", start.nice(), e.summary));
+                    continue;
+                }
+            };
+
+            if Task::validate_key(&id).is_err() {
+                problems.push(format!(
+                    "{} {}: not a valid task key: {}",
+                    start.nice(),
+                    e.summary,
+                    id
+                ));
+                continue;
+            }
+
+            imported.push(Imported {
+                id,
+                title: Some(e.summary.clone()),
+                entry: LogEntry {
+                    start,
+                    end: Some(start + (e.end - e.start)),
+                    note: Some(e.summary.clone()),
+                },
+            });
+        }
+    }
+    Ok(imported)
+}
+
+fn describe(props: &[Property]) -> String {
+    props
+        .iter()
+        .find(|p| p.name == "SUMMARY")
+        .map(|p| unescape(&p.value))
+        .unwrap_or_else(|| "Event without summary".to_string())
+}
+
+/// Joins folded lines, i.e. lines starting with a space or a tab continue the previous one.
+fn unfold(s: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for l in s.lines() {
+        let l = l.trim_end_matches('\r');
+        match (
+            l.strip_prefix(' ').or_else(|| l.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(l.to_string()),
+        }
+    }
+    lines
+}
+
+/// Parses e.g. `DTSTART;TZID=Europe/Berlin:20261016T090000`.
+fn parse_property(line: &str) -> Option<Property> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.to_uppercase();
+    let params = parts
+        .filter_map(|p| {
+            let mut kv = p.splitn(2, '=');
+            Some((
+                kv.next()?.to_uppercase(),
+                kv.next()?.trim_matches('"').to_string(),
+            ))
+        })
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+fn to_event(props: &[Property], zones: &HashMap<String, Zone>) -> Result<Event> {
+    let get = |name: &str| props.iter().find(|p| p.name == name);
+
+    let (start, wall_start, zone) = match get("DTSTART") {
+        Some(p) => (
+            parse_ical_timestamp(p, zones)?,
+            parse_wall_time(p)?,
+            zone_of(p, zones)?,
+        ),
+        None => bail!("Missing DTSTART"),
+    };
+    let end = match (get("DTEND"), get("DURATION")) {
+        (Some(p), _) => parse_ical_timestamp(p, zones)?,
+        (None, Some(p)) => start + parse_ical_duration(&p.value)?,
+        (None, None) => bail!("Missing DTEND"),
+    };
+    if end < start {
+        bail!("DTEND is before DTSTART");
+    }
+
+    let mut exdates = Vec::new();
+    for p in props.iter().filter(|p| p.name == "EXDATE") {
+        for v in p.value.split(',') {
+            let p = Property {
+                name: p.name.clone(),
+                params: p.params.clone(),
+                value: v.to_string(),
+            };
+            exdates.push(parse_ical_timestamp(&p, zones)?);
+        }
+    }
+
+    let uid = get("UID").map(|p| p.value.clone());
+    let recurrence_id = match (get("RECURRENCE-ID"), &uid) {
+        (Some(p), Some(u)) => Some((u.clone(), parse_ical_timestamp(p, zones)?)),
+        _ => None,
+    };
+
+    Ok(Event {
+        summary: get("SUMMARY")
+            .map(|p| unescape(&p.value))
+            .unwrap_or_default(),
+        start,
+        wall_start,
+        zone,
+        end,
+        rrule: get("RRULE").map(|p| p.value.clone()),
+        exdates,
+        recurrence_id,
+        uid,
+    })
+}
+
+fn parse_ical_timestamp(p: &Property, zones: &HashMap<String, Zone>) -> Result<Timestamp> {
+    let wall = parse_wall_time(p)?;
+    from_wall(&wall, zone_of(p, zones)?.as_ref())
+        .with_context(|| format!("Invalid local time: {}", p.value.trim()))
+}
+
+/// The time as written, without its zone.
+fn parse_wall_time(p: &Property) -> Result<NaiveDateTime> {
+    let v = p.value.trim();
+
+    if p.params.get("VALUE").map(|x| x == "DATE").unwrap_or(false) || v.len() == 8 {
+        bail!("All-day events are not supported");
+    }
+
+    NaiveDateTime::parse_from_str(v.trim_end_matches('Z'), ICAL_LOCAL_FORMAT)
+        .with_context(|| format!("Invalid {}: {}", p.name, v))
+}
+
+/// The zone of a time: UTC if it ends with `Z`, the `VTIMEZONE` named by its `TZID`, or `None`
+/// for local time.
+fn zone_of(p: &Property, zones: &HashMap<String, Zone>) -> Result<Option<Zone>> {
+    if p.value.trim().ends_with('Z') {
+        return Ok(Some(Zone::utc()));
+    }
+
+    let tzid = match p.params.get("TZID") {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    if let Some(z) = zones.get(tzid) {
+        return Ok(Some(z.clone()));
+    }
+    match tzid.as_str() {
+        "UTC" | "GMT" | "Etc/UTC" | "Etc/GMT" => Ok(Some(Zone::utc())),
+        t if Some(t) == local_zone_name().as_deref() => Ok(None),
+        t => bail!("Unknown time zone, not defined in the file: {}", t),
+    }
+}
+
+/// The name of the local zone, e.g. `Europe/Berlin`, from `TZ` or the `/etc/localtime` link.
+fn local_zone_name() -> Option<String> {
+    if let Ok(tz) = env::var("TZ") {
+        return Some(tz.trim_start_matches(':').to_string());
+    }
+    let link = fs::read_link("/etc/localtime").ok()?;
+    let link = link.to_string_lossy();
+    link.find("zoneinfo/").map(|i| link[i + 9..].to_string())
+}
+
+fn from_wall(wall: &NaiveDateTime, zone: Option<&Zone>) -> Option<Timestamp> {
+    match zone {
+        Some(z) => Some(to_local(Utc.from_utc_datetime(&(*wall - z.offset(wall))))),
+        None => Local
+            .from_local_datetime(wall)
+            .earliest()
+            .map(|t| to_local(t.with_timezone(&Utc))),
+    }
+}
+
+fn to_wall(t: &Timestamp, zone: Option<&Zone>) -> NaiveDateTime {
+    match zone {
+        Some(z) => z.wall(t),
+        None => t.with_timezone(&Local).naive_local(),
+    }
+}
+
+impl Zone {
+    fn utc() -> Self {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        Zone {
+            observances: vec![Observance {
+                start: NaiveDateTime::MIN,
+                offset_from: utc,
+                offset_to: utc,
+                yearly: None,
+                until: None,
+            }],
+        }
+    }
+
+    /// The offset from UTC at the given wall time, i.e. that of the last change before it.
+    fn offset(&self, wall: &NaiveDateTime) -> Duration {
+        let latest = self
+            .observances
+            .iter()
+            .filter_map(|o| o.last_onset(wall).map(|t| (t, o.offset_to)))
+            .max_by_key(|(t, _)| *t);
+        let offset = match latest {
+            Some((_, o)) => o,
+            None => self
+                .observances
+                .iter()
+                .min_by_key(|o| o.start)
+                .map(|o| o.offset_from)
+                .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap()),
+        };
+        Duration::seconds(i64::from(offset.local_minus_utc()))
+    }
+
+    /// The wall time at the given instant, trying each offset in use until one fits.
+    fn wall(&self, t: &Timestamp) -> NaiveDateTime {
+        let utc = t.naive_utc();
+        self.observances
+            .iter()
+            .map(|o| utc + Duration::seconds(i64::from(o.offset_to.local_minus_utc())))
+            .find(|w| *w - self.offset(w) == utc)
+            .unwrap_or(utc)
+    }
+}
+
+impl Observance {
+    /// When the offset last changed to this one, at or before the given wall time.
+    fn last_onset(&self, wall: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let onset = match self.yearly {
+            None => Some(self.start),
+            Some((month, n, weekday)) => (wall.year() - 1..=wall.year())
+                .rev()
+                .filter_map(|y| nth_weekday(y, month, n, weekday))
+                .map(|d| d.and_time(self.start.time()))
+                .find(|t| t <= wall),
+        };
+        onset.filter(|t| {
+            t <= wall && *t >= self.start && self.until.map(|u| *t <= u).unwrap_or(true)
+        })
+    }
+}
+
+fn to_zone(observances: &[Vec<Property>]) -> Result<Zone> {
+    let observances = observances
+        .iter()
+        .map(|props| {
+            let get = |name: &str| {
+                props
+                    .iter()
+                    .find(|p| p.name == name)
+                    .with_context(|| format!("Missing {}", name))
+            };
+            Ok(Observance {
+                start: parse_wall_time(get("DTSTART")?)?,
+                offset_from: parse_utc_offset(&get("TZOFFSETFROM")?.value)?,
+                offset_to: parse_utc_offset(&get("TZOFFSETTO")?.value)?,
+                yearly: match props.iter().find(|p| p.name == "RRULE") {
+                    Some(p) => Some(parse_yearly(&p.value)?),
+                    None => None,
+                },
+                until: match props.iter().find(|p| p.name == "RRULE") {
+                    Some(p) => parse_until(&p.value)?,
+                    None => None,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if observances.is_empty() {
+        bail!("Time zone without STANDARD or DAYLIGHT");
+    }
+    Ok(Zone { observances })
+}
+
+/// Parses e.g. `+0200`, `-0330` or `+053000`.
+fn parse_utc_offset(x: &str) -> Result<FixedOffset> {
+    let x = x.trim();
+    let n = |r: std::ops::Range<usize>| x.get(r).and_then(|d| d.parse::<i32>().ok());
+
+    let secs = match (x.len(), n(1..3), n(3..5), n(5..7)) {
+        (5, Some(h), Some(m), _) => h * 3600 + m * 60,
+        (7, Some(h), Some(m), Some(s)) => h * 3600 + m * 60 + s,
+        _ => bail!("Invalid UTC offset: {}", x),
+    };
+    match x.chars().next() {
+        Some('+') => FixedOffset::east_opt(secs),
+        Some('-') => FixedOffset::west_opt(secs),
+        _ => None,
+    }
+    .with_context(|| format!("Invalid UTC offset: {}", x))
+}
+
+/// Parses a yearly rule of a time zone, e.g. `FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU`.
+fn parse_yearly(rrule: &str) -> Result<(u32, i64, Weekday)> {
+    let rule: HashMap<&str, &str> = rrule
+        .split(';')
+        .filter_map(|p| {
+            let mut kv = p.splitn(2, '=');
+            Some((kv.next()?, kv.next()?))
+        })
+        .collect();
+
+    match (rule.get("FREQ"), rule.get("BYMONTH"), rule.get("BYDAY")) {
+        (Some(&"YEARLY"), Some(m), Some(d)) if d.len() > 2 => {
+            let (n, day) = d.split_at(d.len() - 2);
+            let month = m.parse::<u32>().ok().filter(|m| (1..=12).contains(m));
+            match (month, n.parse::<i64>()) {
+                (Some(month), Ok(n)) if n != 0 => Ok((month, n, parse_weekday(day)?)),
+                _ => bail!("Unsupported time zone rule: {}", rrule),
+            }
+        }
+        _ => bail!("Unsupported time zone rule: {}", rrule),
+    }
+}
+
+fn parse_until(rrule: &str) -> Result<Option<NaiveDateTime>> {
+    match rrule.split(';').find_map(|p| p.strip_prefix("UNTIL=")) {
+        Some(u) => NaiveDateTime::parse_from_str(u.trim_end_matches('Z'), ICAL_LOCAL_FORMAT)
+            .map(Some)
+            .with_context(|| format!("Invalid UNTIL: {}", u)),
+        None => Ok(None),
+    }
+}
+
+/// The `n`th given weekday of the month, counting from its end if `n` is negative.
+fn nth_weekday(year: i32, month: u32, n: i64, weekday: Weekday) -> Option<NaiveDate> {
+    let day = if n > 0 {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let ahead = (7 + weekday.num_days_from_monday() - first.weekday().num_days_from_monday()) % 7;
+        first + Duration::days(i64::from(ahead)) + Duration::weeks(n - 1)
+    } else {
+        let next = match month {
+            12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+            _ => NaiveDate::from_ymd_opt(year, month + 1, 1)?,
+        };
+        let last = next - Duration::days(1);
+        let back = (7 + last.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        last - Duration::days(i64::from(back)) - Duration::weeks(-n - 1)
+    };
+    Some(day).filter(|d| d.month() == month)
+}
+
+/// Parses e.g. `PT1H30M` or `P1D`.
+fn parse_ical_duration(x: &str) -> Result<Duration> {
+    let m = ICAL_DURATION_RE
+        .captures(x.trim())
+        .with_context(|| format!("Invalid duration: {}", x))?;
+
+    let n = |k: &str| {
+        m.name(k)
+            .map(|v| v.as_str().parse::<i64>().unwrap())
+            .unwrap_or(0)
+    };
+    let d = Duration::weeks(n("w"))
+        + Duration::days(n("d"))
+        + Duration::hours(n("h"))
+        + Duration::minutes(n("min"))
+        + Duration::seconds(n("s"));
+
+    match m.name("sign").map(|s| s.as_str()) {
+        Some("-") => Ok(-d),
+        _ => Ok(d),
+    }
+}
+
+/// Lists the starts of all occurrences of the event up to `before`.
+fn occurrences(e: &Event, before: Timestamp) -> Result<Vec<Timestamp>> {
+    let rrule = match &e.rrule {
+        Some(r) => r,
+        None => return Ok(vec![e.start]),
+    };
+    // Expanded in the event's own zone, so they keep its wall time across its changes
+    let zone = e.zone.as_ref();
+
+    let rule: HashMap<&str, &str> = rrule
+        .split(';')
+        .filter_map(|p| {
+            let mut kv = p.splitn(2, '=');
+            Some((kv.next()?, kv.next()?))
+        })
+        .collect();
+
+    let interval = match rule.get("INTERVAL") {
+        Some(i) => i.parse::<i64>().context("Invalid INTERVAL")?,
+        None => 1,
+    };
+    // Occurrences would never advance
+    if interval < 1 {
+        bail!("Invalid INTERVAL");
+    }
+    let count = match rule.get("COUNT") {
+        Some(c) => Some(c.parse::<usize>().context("Invalid COUNT")?),
+        None => None,
+    };
+    let until = match rule.get("UNTIL") {
+        Some(u) if u.len() == 8 => Some(
+            NaiveDate::parse_from_str(u, "%Y%m%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(23, 59, 59))
+                .context("Invalid UNTIL")?,
+        ),
+        Some(u) => {
+            let p = Property {
+                name: "UNTIL".to_string(),
+                params: HashMap::new(),
+                value: u.to_string(),
+            };
+            Some(to_wall(&parse_ical_timestamp(&p, &HashMap::new())?, zone))
+        }
+        None => None,
+    };
+
+    let first = e.wall_start;
+    let days: Vec<Weekday> = match (rule.get("FREQ"), rule.get("BYDAY")) {
+        (Some(&"DAILY"), _) => Vec::new(),
+        (Some(&"WEEKLY"), None) => vec![first.weekday()],
+        (Some(&"WEEKLY"), Some(b)) => b
+            .split(',')
+            .map(parse_weekday)
+            .collect::<Result<Vec<_>>>()?,
+        _ => bail!("Unsupported recurrence rule: {}", rrule),
+    };
+
+    let mut starts = Vec::new();
+    let mut period = 0;
+    let last = to_wall(&before, zone);
+
+    loop {
+        let candidates: Vec<NaiveDateTime> = if days.is_empty() {
+            vec![first + Duration::days(period * interval)]
+        } else {
+            let week = first - Duration::days(first.weekday().num_days_from_monday() as i64)
+                + Duration::weeks(period * interval);
+            let mut c: Vec<NaiveDateTime> = days
+                .iter()
+                .map(|d| week + Duration::days(d.num_days_from_monday() as i64))
+                .filter(|t| *t >= first)
+                .collect();
+            c.sort();
+            c
+        };
+
+        for t in candidates {
+            if t > last || until.map(|u| t > u).unwrap_or(false) {
+                return Ok(starts);
+            }
+            if count.map(|c| starts.len() >= c).unwrap_or(false) {
+                return Ok(starts);
+            }
+            if let Some(s) = from_wall(&t, zone) {
+                starts.push(s);
+            }
+        }
+        period += 1;
+    }
+}
+
+fn parse_weekday(x: &str) -> Result<Weekday> {
+    match x.trim() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => bail!("Unsupported BYDAY value: {}", x),
+    }
+}
+
+fn unescape(x: &str) -> String {
+    let mut out = String::new();
+    let mut chars = x.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => (),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{DateTime, Duration};
+
+    use super::{escape, fold, parse, parse_ical_duration, uid, unescape};
+    use crate::config::EventRule;
+
+    #[test]
+    fn test_uid() {
+        let t = DateTime::parse_from_rfc3339("2026-10-16T09:00:00-03:00").unwrap();
+
+        assert_eq!(uid("/foo/bar", &t), "20261016T120000Z-foo.bar@dit");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a, b; c\\d\ne"), "a\\, b\\; c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("a\\, b\\; c\\\\d\\ne"), "a, b; c\\d\ne");
+    }
+
+    #[test]
+    fn test_parse_ical_duration() {
+        assert_eq!(
+            parse_ical_duration("PT1H30M").unwrap(),
+            Duration::minutes(90)
+        );
+        assert_eq!(parse_ical_duration("P1W2D").unwrap(), Duration::days(9));
+        assert!(parse_ical_duration("1H").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let s = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:standup\r
+DTSTART:20261012T090000Z\r
+DTEND:20261012T091500Z\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4\r
+EXDATE:20261014T090000Z\r
+SUMMARY:Acme stand\r
+ up\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup\r
+RECURRENCE-ID:20261019T090000Z\r
+DTSTART:20261019T100000Z\r
+DURATION:PT30M\r
+SUMMARY:Acme standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20261012\r
+SUMMARY:Holiday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20261013T090000Z\r
+DTEND:20261013T100000Z\r
+SUMMARY:Lunch\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let rules = vec![EventRule {
+            summary: "^(Acme) standup".to_string(),
+            task: "clients/$1/meetings".to_string(),
+        }];
+        let before = DateTime::parse_from_rfc3339("2026-10-31T00:00:00Z").unwrap();
+
+        let mut problems = Vec::new();
+        let imported = parse(s, &rules, None, before, &mut problems).unwrap();
+
+        let starts: Vec<i64> = imported.iter().map(|i| i.entry.start.timestamp()).collect();
+        let expected: Vec<i64> = [
+            "2026-10-12T09:00:00Z",
+            "2026-10-21T09:00:00Z",
+            "2026-10-19T10:00:00Z",
+        ]
+        .iter()
+        .map(|x| DateTime::parse_from_rfc3339(x).unwrap().timestamp())
+        .collect();
+
+        assert_eq!(starts, expected);
+        assert!(imported.iter().all(|i| i.id == "clients/Acme/meetings"));
+        assert_eq!(imported[2].entry.effort(), Duration::minutes(30));
+
+        // The all-day event, and the one no rule matches
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_time_zones() {
+        let s = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/New_York:20261014T090000\r
+DTEND;TZID=America/New_York:20261014T100000\r
+SUMMARY:Acme review\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/New_York:20261026T090000\r
+DURATION:PT15M\r
+RRULE:FREQ=WEEKLY;COUNT=2\r
+SUMMARY:Acme standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Mars/Olympus_Mons:20261014T090000\r
+DURATION:PT1H\r
+SUMMARY:Acme planning\r
+END:VEVENT\r
+BEGIN:VTIMEZONE\r
+TZID:America/New_York\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:-0500\r
+TZOFFSETTO:-0400\r
+DTSTART:19700308T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:-0400\r
+TZOFFSETTO:-0500\r
+DTSTART:19701101T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+END:VCALENDAR\r
+";
+        let rules = vec![EventRule {
+            summary: "^(Acme) ".to_string(),
+            task: "clients/$1/meetings".to_string(),
+        }];
+        let before = DateTime::parse_from_rfc3339("2026-11-30T00:00:00Z").unwrap();
+
+        let mut problems = Vec::new();
+        let imported = parse(s, &rules, None, before, &mut problems).unwrap();
+
+        // Summer time in New York ends on 2026-11-01, so the standup moves an hour in UTC
+        let starts: Vec<i64> = imported.iter().map(|i| i.entry.start.timestamp()).collect();
+        let expected: Vec<i64> = [
+            "2026-10-14T13:00:00Z",
+            "2026-10-26T13:00:00Z",
+            "2026-11-02T14:00:00Z",
+        ]
+        .iter()
+        .map(|x| DateTime::parse_from_rfc3339(x).unwrap().timestamp())
+        .collect();
+
+        assert_eq!(starts, expected);
+        assert_eq!(imported[0].entry.effort(), Duration::hours(1));
+        assert_eq!(
+            problems,
+            vec!["Acme planning: Unknown time zone, not defined in the file: Mars/Olympus_Mons"]
+        );
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        let s = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART:20261012T090000Z\r
+DTEND:20261012T091500Z\r
+RRULE:FREQ=DAILY;INTERVAL=0\r
+SUMMARY:Acme standup\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let rules = vec![EventRule {
+            summary: "^(Acme) standup".to_string(),
+            task: "clients/$1/meetings".to_string(),
+        }];
+        let before = DateTime::parse_from_rfc3339("2026-10-31T00:00:00Z").unwrap();
+
+        let mut problems = Vec::new();
+        let imported = parse(s, &rules, None, before, &mut problems).unwrap();
+
+        assert!(imported.is_empty());
+        assert_eq!(problems, vec!["Acme standup: Invalid INTERVAL"]);
+    }
+
+    #[test]
+    fn test_fold() {
+        let l = "x".repeat(80);
+        let f = fold(&l);
+
+        assert_eq!(f, format!("{}\r\n {}\r\n", "x".repeat(75), "x".repeat(5)));
+    }
+}
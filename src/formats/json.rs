@@ -0,0 +1,141 @@
+//! Reads and writes the whole repository as a single, versioned JSON document, e.g.
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "exported": "2026-10-16 18:00:00 +0100",
+//!   "tasks": [
+//!     { "id": "clients/acme/api", "title": "The API", "estimate": "10h", "log": [...] }
+//!   ],
+//!   "budgets": { "clients/acme": { "estimate": "40h" } }
+//! }
+//! ```
+//!
+//! Timestamps and durations are written as in the task files.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::models::{Budget, Budgets, Task, TaskData};
+use crate::utils::time::Timestamp;
+
+/// Bumped whenever the document changes in ways older versions of dit cannot read.
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Document {
+    pub version: u32,
+    #[serde(with = "crate::utils::time::timestamp")]
+    pub exported: Timestamp,
+    pub tasks: Vec<ExportedTask>,
+    #[serde(default)]
+    pub budgets: BTreeMap<String, Budget>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedTask {
+    pub id: String,
+    #[serde(flatten)]
+    pub data: TaskData,
+}
+
+pub fn export(tasks: Vec<Task>, budgets: Budgets, exported: Timestamp) -> Result<String> {
+    let document = Document {
+        version: VERSION,
+        exported,
+        tasks: tasks
+            .into_iter()
+            .map(|t| ExportedTask {
+                id: t.id,
+                data: t.data,
+            })
+            .collect(),
+        budgets: budgets.into_iter().collect(),
+    };
+
+    serde_json::to_string_pretty(&document).context("Could not serialize repository")
+}
+
+pub fn parse(s: &str) -> Result<Document> {
+    #[derive(Deserialize)]
+    struct Versioned {
+        version: u32,
+    }
+
+    // Check the version first, so newer documents fail with a useful message
+    let v: Versioned = serde_json::from_str(s).context("Could not parse JSON document")?;
+    if v.version > VERSION {
+        bail!(
+            "Unsupported document version: {} (at most {} is supported)",
+            v.version,
+            VERSION
+        );
+    }
+
+    let document: Document = serde_json::from_str(s).context("Could not parse JSON document")?;
+
+    for t in document.tasks.iter() {
+        if Task::validate_key(&t.id).is_err() {
+            bail!("Not a valid task key: {}", t.id);
+        }
+    }
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{DateTime, Duration};
+
+    use super::{export, parse};
+    use crate::models::{Budget, Budgets, LogEntry, Task};
+
+    #[test]
+    fn test_round_trip() {
+        let t0 = DateTime::parse_from_str("2026-10-16 09:00:00 +0000", "%F %T %z").unwrap();
+
+        let mut task = Task::new("clients/acme/api".to_string());
+        task.data.title = "The API".to_string();
+        task.data.estimate = Some(Duration::hours(10));
+        task.data.log.push(LogEntry {
+            start: t0,
+            end: Some(t0 + Duration::minutes(90)),
+            note: Some("Design".to_string()),
+        });
+
+        let mut budgets = Budgets::new();
+        budgets.insert(
+            "clients/acme".to_string(),
+            Budget {
+                estimate: Duration::hours(40),
+            },
+        );
+
+        let mut open = Task::new("foo".to_string());
+        open.data.log.push(LogEntry::new(t0));
+
+        let s = export(vec![task, open], budgets, t0).unwrap();
+        let d = parse(&s).unwrap();
+
+        assert_eq!(d.version, 1);
+        assert_eq!(d.tasks.len(), 2);
+        assert_eq!(d.tasks[0].id, "clients/acme/api");
+        assert_eq!(d.tasks[0].data.estimate, Some(Duration::hours(10)));
+        assert_eq!(d.tasks[0].data.log[0].end, Some(t0 + Duration::minutes(90)));
+        assert_eq!(d.tasks[0].data.log[0].note.as_deref(), Some("Design"));
+        assert_eq!(d.tasks[1].data.estimate, None);
+        assert!(d.tasks[1].data.log[0].is_open());
+        assert_eq!(d.budgets["clients/acme"].estimate, Duration::hours(40));
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert!(parse(r#"{"version": 2, "tasks": []}"#).is_err());
+        assert!(parse(r#"{"tasks": []}"#).is_err());
+        assert!(parse(
+            r#"{"version": 1, "exported": "2026-10-16 09:00:00 +0000", "tasks": [{"id": "1x", "title": "x"}]}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::models::LogEntry;
+
+pub mod csv;
+pub mod dit_v1;
+pub mod html;
+pub mod ical;
+pub mod json;
+pub mod org;
+pub mod timeclock;
+pub mod timewarrior;
+
+/// A log entry read from another tool, along with the task it belongs to.
+pub struct Imported {
+    pub id: String,
+    /// Title to use if the task has to be created.
+    pub title: Option<String>,
+    pub entry: LogEntry,
+}
+
+/// Compiles a rule's pattern; `anchored` makes it match the whole text only.
+pub fn rule_regex(pattern: &str, anchored: bool) -> Result<Regex> {
+    let p = match anchored {
+        true => format!("^(?:{})$", pattern),
+        false => pattern.to_string(),
+    };
+    Regex::new(&p).with_context(|| format!("Invalid rule pattern: {}", pattern))
+}
+
+/// Builds the task key of a matching rule, where `template` may refer to capture groups, e.g.
+/// `clients/$1`.
+pub fn apply_rule(re: &Regex, template: &str, text: &str) -> Option<String> {
+    re.captures(text).map(|c| {
+        let mut key = String::new();
+        c.expand(template, &mut key);
+        key
+    })
+}
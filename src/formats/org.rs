@@ -0,0 +1,354 @@
+//! Reads and writes org-mode `CLOCK:` lines, and prints clocktable-like summaries.
+//!
+//! Tasks are written as headlines mirroring the key hierarchy, so `clients/acme/api` becomes
+//! `*** api` under `** acme` under `* clients`. Org timestamps have no timezone, so they are read
+//! in, and written out as, local time.
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Local, NaiveDateTime, TimeZone, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+use crate::formats::Imported;
+use crate::models::{ListItem, LogEntry, Task};
+use crate::utils::time::{to_local, Timestamp};
+
+lazy_static! {
+    static ref HEADLINE_RE: Regex = Regex::new(r"^(?P<stars>\*+)\s+(?P<text>.*?)\s*$").unwrap();
+    static ref CLOCK_RE: Regex =
+        Regex::new(r"^\s*CLOCK:\s*\[(?P<start>[^\]]+)\](?:--\[(?P<end>[^\]]+)\])?").unwrap();
+    static ref ORG_TIMESTAMP_RE: Regex =
+        Regex::new(r"^(?P<d>\d{4}-\d{2}-\d{2})(?:\s+\S+)?\s+(?P<t>\d{1,2}:\d{2})$").unwrap();
+    static ref TITLE_PROPERTY_RE: Regex = Regex::new(r"^\s*:TITLE:\s*(?P<v>.*?)\s*$").unwrap();
+    static ref TODO_RE: Regex = Regex::new(r"^(?:TODO|DONE|NEXT|WAITING)\s+").unwrap();
+    static ref TAGS_RE: Regex = Regex::new(r"\s+:[\w@#%:]+:$").unwrap();
+    static ref KEY_CHARS_RE: Regex = Regex::new(r"[^0-9A-Za-z_-]+").unwrap();
+}
+
+const ORG_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %a %H:%M";
+
+struct Node {
+    key: Option<String>,
+    title: String,
+}
+
+/// Reads the `CLOCK:` lines of an org file. Each one belongs to the task whose key is made of
+/// the enclosing headlines, e.g. `clients/acme/api`.
+pub fn parse(s: &str, problems: &mut Vec<String>) -> Vec<Imported> {
+    let mut path: Vec<Node> = Vec::new();
+    let mut imported = Vec::new();
+
+    for (n, line) in s.lines().enumerate() {
+        if let Some(m) = HEADLINE_RE.captures(line) {
+            let level = m["stars"].len();
+            let text = TAGS_RE
+                .replace(TODO_RE.replace(&m["text"], "").as_ref(), "")
+                .to_string();
+
+            path.truncate(level - 1);
+            while path.len() < level - 1 {
+                path.push(Node {
+                    key: None,
+                    title: String::new(),
+                });
+            }
+            path.push(Node {
+                key: to_key_component(&text),
+                title: text,
+            });
+            continue;
+        }
+
+        if let Some(m) = TITLE_PROPERTY_RE.captures(line) {
+            if let Some(node) = path.last_mut() {
+                node.title = m["v"].to_string();
+            }
+            continue;
+        }
+
+        if let Some(m) = CLOCK_RE.captures(line) {
+            let node = match path.last() {
+                Some(node) => node,
+                None => {
+                    problems.push(format!("line {}: clocked outside of any headline", n + 1));
+                    continue;
+                }
+            };
+            let id = match key_of(&path) {
+                Some(id) => id,
+                None => {
+                    problems.push(format!("line {}: headlines do not make a task key", n + 1));
+                    continue;
+                }
+            };
+
+            let entry = parse_clock(&m["start"], m.name("end").map(|x| x.as_str()));
+            match entry {
+                Ok(entry) => imported.push(Imported {
+                    id,
+                    title: Some(node.title.clone()),
+                    entry,
+                }),
+                Err(e) => problems.push(format!("line {}: {:#}", n + 1, e)),
+            }
+        }
+    }
+
+    imported
+}
+
+fn key_of(path: &[Node]) -> Option<String> {
+    let parts = path
+        .iter()
+        .map(|n| n.key.clone())
+        .collect::<Option<Vec<_>>>()?;
+
+    let key = parts.join("/");
+    Task::validate_key(&key).ok().map(|_| key)
+}
+
+/// Turns a headline into a key component, e.g. "Write report" into "Write-report".
+fn to_key_component(text: &str) -> Option<String> {
+    let c = KEY_CHARS_RE.replace_all(text.trim(), "-");
+    let c = c.trim_matches('-');
+
+    match Task::validate_key(c) {
+        Ok(()) if !c.contains('/') => Some(c.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_clock(start: &str, end: Option<&str>) -> Result<LogEntry> {
+    let start = parse_org_timestamp(start)?;
+    let end = match end {
+        Some(e) => Some(parse_org_timestamp(e)?),
+        None => None,
+    };
+    Ok(LogEntry {
+        start,
+        end,
+        note: None,
+    })
+}
+
+fn parse_org_timestamp(x: &str) -> Result<Timestamp> {
+    let m = ORG_TIMESTAMP_RE
+        .captures(x.trim())
+        .with_context(|| format!("Invalid timestamp: {}", x))?;
+
+    let naive =
+        NaiveDateTime::parse_from_str(&format!("{} {}", &m["d"], &m["t"]), "%Y-%m-%d %H:%M")
+            .with_context(|| format!("Invalid timestamp: {}", x))?;
+
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| to_local(t.with_timezone(&Utc)))
+        .with_context(|| format!("Invalid local time: {}", x))
+}
+
+fn format_org_timestamp(x: &Timestamp) -> String {
+    x.with_timezone(&Local)
+        .format(ORG_TIMESTAMP_FORMAT)
+        .to_string()
+}
+
+/// Formats as org does, i.e. `H:MM`.
+fn format_org_duration(x: &Duration) -> String {
+    let m = x.num_minutes();
+    format!("{}:{:02}", m / 60, m % 60)
+}
+
+fn key_parts(id: &str) -> Vec<String> {
+    id.trim_start_matches('/')
+        .split('/')
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Writes the entries as headlines with `:LOGBOOK:` drawers, most recent entries first.
+pub fn export(items: &[ListItem]) -> String {
+    let mut tasks: BTreeMap<Vec<String>, (&str, Vec<&LogEntry>)> = BTreeMap::new();
+
+    for x in items {
+        tasks
+            .entry(key_parts(&x.id))
+            .or_insert_with(|| (x.title.as_str(), Vec::new()))
+            .1
+            .push(&x.log_entry);
+    }
+
+    let mut out = String::new();
+    let mut previous: Vec<String> = Vec::new();
+
+    for (parts, (title, entries)) in tasks {
+        let common = previous
+            .iter()
+            .zip(parts.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        for (level, part) in parts.iter().enumerate().skip(common) {
+            out.push_str(&format!("{} {}\n", "*".repeat(level + 1), part));
+        }
+
+        out.push_str(":PROPERTIES:\n");
+        out.push_str(&format!(":TITLE: {}\n", title));
+        out.push_str(":END:\n");
+        out.push_str(":LOGBOOK:\n");
+        for e in entries {
+            match e.end {
+                Some(end) => out.push_str(&format!(
+                    "CLOCK: [{}]--[{}] => {:>5}\n",
+                    format_org_timestamp(&e.start),
+                    format_org_timestamp(&end),
+                    format_org_duration(&(end - e.start)),
+                )),
+                None => out.push_str(&format!("CLOCK: [{}]\n", format_org_timestamp(&e.start))),
+            }
+        }
+        out.push_str(":END:\n");
+
+        previous = parts;
+    }
+    out
+}
+
+/// Summarizes the effort per headline, like org's clocktable dynamic block.
+pub fn clocktable(items: &[ListItem]) -> String {
+    let mut totals: BTreeMap<Vec<String>, Duration> = BTreeMap::new();
+    let mut total = Duration::zero();
+
+    for x in items {
+        let parts = key_parts(&x.id);
+        for i in 1..=parts.len() {
+            let t = totals
+                .entry(parts[..i].to_vec())
+                .or_insert_with(Duration::zero);
+            *t += x.effort();
+        }
+        total += x.effort();
+    }
+
+    let depth = totals.keys().map(|k| k.len()).max().unwrap_or(1);
+
+    let mut rows: Vec<Vec<String>> = vec![{
+        let mut r = vec![
+            "*Total time*".to_string(),
+            format!("*{}*", format_org_duration(&total)),
+        ];
+        r.resize(depth + 1, String::new());
+        r
+    }];
+
+    for (parts, effort) in totals.iter() {
+        let level = parts.len();
+        let mut r = vec![String::new(); depth + 1];
+        r[0] = match level {
+            1 => parts[0].clone(),
+            _ => format!(
+                "\\_{} {}",
+                " ".repeat(2 * (level - 1) - 1),
+                parts[level - 1]
+            ),
+        };
+        r[level] = format_org_duration(effort);
+        rows.push(r);
+    }
+
+    let mut header = vec!["Headline".to_string(), "Time".to_string()];
+    header.resize(depth + 1, String::new());
+
+    let widths: Vec<usize> = (0..=depth)
+        .map(|i| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|r| r[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let line = |r: &Vec<String>| {
+        let cells: Vec<String> = r
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (c, w))| match i {
+                0 => format!("{:<width$}", c, width = w),
+                _ => format!("{:>width$}", c, width = w),
+            })
+            .collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let sep = {
+        let s: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+        format!("|{}|\n", s.join("+"))
+    };
+
+    let mut out = String::from("#+BEGIN: clocktable :scope dit\n");
+    out.push_str(&line(&header));
+    out.push_str(&sep);
+    out.push_str(&line(&rows[0]));
+    out.push_str(&sep);
+    for r in rows.iter().skip(1) {
+        out.push_str(&line(r));
+    }
+    out.push_str("#+END:\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::Duration;
+
+    use super::{format_org_duration, parse, to_key_component};
+
+    #[test]
+    fn test_parse() {
+        let s = "\
+#+TITLE: Work
+CLOCK: [2026-10-16 Fri 08:00]--[2026-10-16 Fri 08:30] =>  0:30
+* clients
+** acme
+*** TODO API work :urgent:
+:PROPERTIES:
+:TITLE: The API
+:END:
+:LOGBOOK:
+CLOCK: [2026-10-16 Fri 09:00]--[2026-10-16 Fri 10:30] =>  1:30
+CLOCK: [2026-10-16 Fri 11:00]
+:END:
+* 1 bad
+CLOCK: [2026-10-16 Fri 09:00]--[2026-10-16 Fri 10:30] =>  1:30
+";
+        let mut problems = Vec::new();
+        let imported = parse(s, &mut problems);
+
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].id, "clients/acme/API-work");
+        assert_eq!(imported[0].title.as_deref(), Some("The API"));
+        assert_eq!(imported[0].entry.effort(), Duration::minutes(90));
+        assert!(imported[1].entry.is_open());
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("line 2:"));
+    }
+
+    #[test]
+    fn test_to_key_component() {
+        assert_eq!(
+            to_key_component("Write report"),
+            Some("Write-report".to_string())
+        );
+        assert_eq!(to_key_component("api"), Some("api".to_string()));
+        assert_eq!(to_key_component("2021 plans"), None);
+    }
+
+    #[test]
+    fn test_format_org_duration() {
+        assert_eq!(format_org_duration(&Duration::minutes(90)), "1:30");
+        assert_eq!(format_org_duration(&Duration::hours(30)), "30:00");
+    }
+}
@@ -0,0 +1,181 @@
+//! Reads and writes the timeclock format understood by ledger and hledger, e.g.
+//!
+//! ```text
+//! i 2026/10/16 09:00:00 clients:acme:api  Some title
+//! o 2026/10/16 10:30:00
+//! ```
+//!
+//! Task keys map to accounts by replacing '/' with ':'. Timeclock files have no timezone, so
+//! times are read in, and written out as, local time.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+
+use crate::formats::Imported;
+use crate::models::{ListItem, LogEntry, Task};
+use crate::utils::time::{to_local, Timestamp};
+
+const TIMECLOCK_FORMAT: &str = "%Y/%m/%d %H:%M:%S";
+
+pub fn to_account(id: &str) -> String {
+    id.trim_start_matches('/').replace('/', ":")
+}
+
+pub fn to_key(account: &str) -> String {
+    account.replace(':', "/")
+}
+
+/// Writes the entries in chronological order, as timeclock files must be.
+pub fn export(items: &[ListItem]) -> String {
+    let mut out = String::new();
+
+    for x in items.iter().rev() {
+        out.push_str(&format!(
+            "i {} {}  {}\n",
+            format_timeclock_timestamp(&x.start()),
+            to_account(&x.id),
+            x.title
+        ));
+        if let Some(end) = x.end() {
+            out.push_str(&format!("o {}\n", format_timeclock_timestamp(&end)));
+        }
+    }
+    out
+}
+
+/// Pairs up the `i` and `o` lines, describing whatever could not be converted in `problems`.
+pub fn parse(s: &str, problems: &mut Vec<String>) -> Vec<Imported> {
+    let mut imported = Vec::new();
+    let mut open: Option<(usize, Imported)> = None;
+
+    for (n, line) in s.lines().enumerate() {
+        let line = line.trim_end();
+
+        // Only `i`/`o` lines matter; other directives like `include` or `b` are skipped
+        let mut chars = line.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), Some(' ')) | (Some(c), Some('\t')) => c,
+            _ => continue,
+        };
+
+        match code {
+            'i' | 'I' => match parse_clock_in(&line[1..]) {
+                Ok(i) => {
+                    if let Some((m, _)) = open.replace((n, i)) {
+                        problems.push(format!("line {}: clock-in without clock-out", m + 1));
+                    }
+                }
+                Err(e) => problems.push(format!("line {}: {:#}", n + 1, e)),
+            },
+            'o' | 'O' => match (open.take(), parse_timestamp_prefix(&line[1..])) {
+                (Some((m, mut i)), Ok((end, _))) => {
+                    if end < i.entry.start {
+                        problems.push(format!("line {}: clock-out before clock-in", m + 1));
+                        continue;
+                    }
+                    i.entry.end = Some(end);
+                    imported.push(i);
+                }
+                (None, _) => problems.push(format!("line {}: clock-out without clock-in", n + 1)),
+                (_, Err(e)) => problems.push(format!("line {}: {:#}", n + 1, e)),
+            },
+            _ => continue,
+        }
+    }
+
+    // The last clock-in may still be running
+    if let Some((_, i)) = open {
+        imported.push(i);
+    }
+    imported
+}
+
+fn parse_clock_in(s: &str) -> Result<Imported> {
+    let (start, rest) = parse_timestamp_prefix(s)?;
+
+    let rest = rest.trim_start();
+    let (account, description) = match rest.find("  ").or_else(|| rest.find('\t')) {
+        Some(i) => (&rest[..i], Some(rest[i..].trim())),
+        None => (rest, None),
+    };
+
+    let id = to_key(account.trim());
+    if Task::validate_key(&id).is_err() {
+        bail!("Not a valid task key: {}", id);
+    }
+
+    Ok(Imported {
+        id,
+        title: description.filter(|d| !d.is_empty()).map(|d| d.to_string()),
+        entry: LogEntry::new(start),
+    })
+}
+
+/// Parses the date and time at the start of the text, returning the rest of it.
+fn parse_timestamp_prefix(s: &str) -> Result<(Timestamp, &str)> {
+    let s = s.trim_start();
+
+    let mut parts = s.splitn(3, ' ');
+    let date = parts.next().unwrap_or_default().replace('-', "/");
+    let time = parts.next().unwrap_or_default();
+    let rest = parts.next().unwrap_or_default();
+
+    let time = match time.matches(':').count() {
+        1 => format!("{}:00", time),
+        _ => time.to_string(),
+    };
+
+    let naive = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), TIMECLOCK_FORMAT)
+        .with_context(|| format!("Invalid date/time: {} {}", date, time))?;
+
+    let t = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| to_local(t.with_timezone(&Utc)))
+        .with_context(|| format!("Invalid local time: {} {}", date, time))?;
+
+    Ok((t, rest))
+}
+
+fn format_timeclock_timestamp(x: &Timestamp) -> String {
+    x.with_timezone(&Local).format(TIMECLOCK_FORMAT).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::Duration;
+
+    use super::{parse, to_account, to_key};
+
+    #[test]
+    fn test_accounts() {
+        assert_eq!(to_account("clients/acme/api"), "clients:acme:api");
+        assert_eq!(to_account("/foo"), "foo");
+        assert_eq!(to_key("clients:acme:api"), "clients/acme/api");
+    }
+
+    #[test]
+    fn test_parse() {
+        let s = "\
+; comment
+include other.timeclock
+i 2026/10/16 09:00:00 clients:acme:api  The API
+o 2026/10/16 10:30:00
+
+i 2026-10-16 11:00 meetings
+o 2026/10/16 10:00:00
+o 2026/10/16 12:00:00
+i 2026/10/16 13:00:00 clients:acme:api
+";
+        let mut problems = Vec::new();
+        let imported = parse(s, &mut problems);
+
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].id, "clients/acme/api");
+        assert_eq!(imported[0].title.as_deref(), Some("The API"));
+        assert_eq!(imported[0].entry.effort(), Duration::minutes(90));
+        assert!(imported[1].entry.is_open());
+        assert_eq!(problems.len(), 2);
+    }
+}
@@ -0,0 +1,271 @@
+//! Reads and writes Timewarrior intervals, both from `timew export` JSON and from the `*.data`
+//! files of its database.
+
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::TagRule;
+use crate::formats::{apply_rule, rule_regex, Imported};
+use crate::models::{ListItem, LogEntry, Task};
+use crate::utils::time::{to_local, Timestamp};
+
+const TIMEW_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Serialize, Deserialize)]
+pub struct Interval {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+    pub start: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<String>,
+}
+
+pub fn parse_export(s: &str) -> Result<Vec<Interval>> {
+    serde_json::from_str(s).context("Could not parse Timewarrior export")
+}
+
+/// Parses the lines of a database file, e.g.
+/// `inc 20261016T090000Z - 20261016T103000Z # foo "a b"`.
+pub fn parse_data(s: &str) -> Result<Vec<Interval>> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(n, l)| parse_data_line(l).with_context(|| format!("Invalid line {}: {}", n + 1, l)))
+        .collect()
+}
+
+fn parse_data_line(l: &str) -> Result<Interval> {
+    let l = match l.trim().strip_prefix("inc ") {
+        Some(l) => l,
+        None => bail!("Expected 'inc'"),
+    };
+
+    let (range, rest) = match l.find('#') {
+        Some(i) => (&l[..i], Some(&l[i + 1..])),
+        None => (l, None),
+    };
+
+    let range: Vec<&str> = range.split_whitespace().collect();
+    let (start, end) = match range.as_slice() {
+        [s] => (s.to_string(), None),
+        [s, "-", e] => (s.to_string(), Some(e.to_string())),
+        _ => bail!("Invalid interval"),
+    };
+
+    let (tags, annotation) = match rest {
+        Some(r) => {
+            let words = split_words(r)?;
+            match words.iter().position(|w| w.0 == "#" && !w.1) {
+                Some(i) => (
+                    words[..i].iter().map(|w| w.0.clone()).collect(),
+                    Some(
+                        words[i + 1..]
+                            .iter()
+                            .map(|w| w.0.clone())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                ),
+                None => (words.into_iter().map(|w| w.0).collect(), None),
+            }
+        }
+        None => (Vec::new(), None),
+    };
+
+    Ok(Interval {
+        id: None,
+        start,
+        end,
+        tags,
+        annotation,
+    })
+}
+
+/// Splits on whitespace, honoring double quotes; also tells whether each word was quoted.
+fn split_words(s: &str) -> Result<Vec<(String, bool)>> {
+    let mut words = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => continue,
+            '"' => {
+                let mut w = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => w.extend(chars.next()),
+                        Some('"') => break,
+                        Some(c) => w.push(c),
+                        None => bail!("Unterminated quote"),
+                    }
+                }
+                words.push((w, true));
+            }
+            c => {
+                let mut w = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    w.push(*c);
+                    chars.next();
+                }
+                words.push((w, false));
+            }
+        }
+    }
+    Ok(words)
+}
+
+fn parse_timew_timestamp(x: &str) -> Result<Timestamp> {
+    NaiveDateTime::parse_from_str(x, TIMEW_FORMAT)
+        .map(|t| to_local(Utc.from_utc_datetime(&t)))
+        .with_context(|| format!("Invalid timestamp: {}", x))
+}
+
+fn format_timew_timestamp(x: &Timestamp) -> String {
+    x.with_timezone(&Utc).format(TIMEW_FORMAT).to_string()
+}
+
+/// Finds the task key for the given tags; the first rule whose pattern matches a tag wins. If no
+/// rule matches, the first tag that is a valid task key is used.
+pub fn map_tags(rules: &[TagRule], tags: &[String]) -> Result<Option<String>> {
+    for rule in rules {
+        let re = rule_regex(&rule.tag, true)?;
+
+        if let Some(key) = tags.iter().find_map(|t| apply_rule(&re, &rule.task, t)) {
+            return Ok(Some(key));
+        }
+    }
+    Ok(tags.iter().find(|t| Task::validate_key(t).is_ok()).cloned())
+}
+
+/// Converts the intervals, describing the ones that could not be converted in `problems`.
+pub fn convert(
+    intervals: &[Interval],
+    rules: &[TagRule],
+    problems: &mut Vec<String>,
+) -> Result<Vec<Imported>> {
+    let mut imported = Vec::new();
+
+    for i in intervals {
+        let desc = format!("{} {}", i.start, i.tags.join(" "));
+
+        let id = match map_tags(rules, &i.tags)? {
+            Some(id) if Task::validate_key(&id).is_ok() => id,
+            Some(id) => {
+                problems.push(format!("{}: not a valid task key: {}", desc, id));
+                continue;
+            }
+            None => {
+                problems.push(format!("{}: no tag maps to a task", desc));
+                continue;
+            }
+        };
+
+        let entry = match convert_times(i) {
+            Ok(e) => e,
+            Err(e) => {
+                problems.push(format!("{}: {:#}", desc, e));
+                continue;
+            }
+        };
+
+        imported.push(Imported {
+            id,
+            title: None,
+            entry,
+        });
+    }
+    Ok(imported)
+}
+
+fn convert_times(i: &Interval) -> Result<LogEntry> {
+    let start = parse_timew_timestamp(&i.start)?;
+    let end = match &i.end {
+        Some(e) => Some(parse_timew_timestamp(e)?),
+        None => None,
+    };
+    Ok(LogEntry {
+        start,
+        end,
+        note: i.annotation.clone(),
+    })
+}
+
+pub fn export(items: &[ListItem]) -> Result<String> {
+    let intervals: Vec<Interval> = items
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(n, x)| Interval {
+            id: Some(n + 1),
+            start: format_timew_timestamp(&x.start()),
+            end: x.end().as_ref().map(format_timew_timestamp),
+            tags: vec![x.id.clone()],
+            annotation: Some(x.log_entry.note.clone().unwrap_or_else(|| x.title.clone())),
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&intervals).context("Could not serialize intervals")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{map_tags, parse_data_line, parse_timew_timestamp};
+    use crate::config::TagRule;
+
+    #[test]
+    fn test_parse_data_line() {
+        let i = parse_data_line(
+            r#"inc 20261016T090000Z - 20261016T103000Z # foo "bar baz" # "some \"note\"""#,
+        )
+        .unwrap();
+
+        assert_eq!(i.start, "20261016T090000Z");
+        assert_eq!(i.end.as_deref(), Some("20261016T103000Z"));
+        assert_eq!(i.tags, vec!["foo", "bar baz"]);
+        assert_eq!(i.annotation.as_deref(), Some("some \"note\""));
+
+        let i = parse_data_line("inc 20261016T090000Z").unwrap();
+
+        assert_eq!(i.end, None);
+        assert!(i.tags.is_empty());
+
+        assert!(parse_data_line("exc 20261016T090000Z").is_err());
+        assert!(parse_data_line("inc 20261016T090000Z - ").is_err());
+    }
+
+    #[test]
+    fn test_parse_timew_timestamp() {
+        let t = parse_timew_timestamp("20261016T090000Z").unwrap();
+
+        assert_eq!(t.timestamp(), 1792141200);
+        assert!(parse_timew_timestamp("2026-10-16").is_err());
+    }
+
+    #[test]
+    fn test_map_tags() {
+        let rules = vec![TagRule {
+            tag: "acme-(.*)".to_string(),
+            task: "clients/acme/$1".to_string(),
+        }];
+        let tags = |x: &[&str]| x.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            map_tags(&rules, &tags(&["meeting", "acme-api"])).unwrap(),
+            Some("clients/acme/api".to_string())
+        );
+        assert_eq!(
+            map_tags(&rules, &tags(&["1x", "meeting"])).unwrap(),
+            Some("meeting".to_string())
+        );
+        assert_eq!(map_tags(&rules, &tags(&["1x"])).unwrap(), None);
+    }
+}
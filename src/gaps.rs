@@ -0,0 +1,106 @@
+//! Finds the working hours in which no entry of any task is open: time that was most likely
+//! worked but not tracked, e.g. after forgetting to clock in again after lunch.
+
+use chrono::Duration;
+
+use crate::models::ListItem;
+use crate::utils::time::Timestamp;
+
+#[derive(Debug, PartialEq)]
+pub struct Gap {
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+impl Gap {
+    pub fn length(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// The stretches of `hours` up to `now` that no entry covers, ignoring those shorter than `min`.
+/// Open entries last until `now`.
+pub fn find(
+    hours: &[(Timestamp, Timestamp)],
+    data: &[ListItem],
+    min: Duration,
+    now: Timestamp,
+) -> Vec<Gap> {
+    let mut entries: Vec<(Timestamp, Timestamp)> = data
+        .iter()
+        .map(|x| (x.start(), x.end().unwrap_or(now)))
+        .collect();
+    entries.sort();
+
+    let mut gaps = Vec::new();
+    for (start, end) in hours {
+        let end = *end.min(&now);
+        let mut covered = *start;
+
+        for (s, e) in entries.iter().filter(|(s, e)| *s < end && *e > *start) {
+            if *s > covered {
+                gaps.push(Gap {
+                    start: covered,
+                    end: *s,
+                });
+            }
+            covered = covered.max(*e);
+        }
+        if covered < end {
+            gaps.push(Gap {
+                start: covered,
+                end,
+            });
+        }
+    }
+
+    gaps.retain(|g| g.length() >= min);
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{find, Gap};
+    use crate::models::list_item;
+    use crate::utils::time::parse_timestamp;
+    use chrono::Duration;
+
+    #[test]
+    fn test_find() {
+        let t = |x| parse_timestamp(x).unwrap();
+        let hours = vec![
+            (t("2021-03-01-08:00"), t("2021-03-01-12:00")),
+            (t("2021-03-01-13:00"), t("2021-03-01-17:00")),
+        ];
+        let data = vec![
+            list_item("acme/api", "2021-03-01-15:00", None),
+            list_item("acme/api", "2021-03-01-11:00", Some("2021-03-01-13:30")),
+            list_item("acme/api", "2021-03-01-07:30", Some("2021-03-01-09:00")),
+            list_item("acme/api", "2021-03-01-09:00", Some("2021-03-01-10:57")),
+        ];
+
+        let gaps = find(&hours, &data, Duration::minutes(5), t("2021-03-01-16:00"));
+        assert_eq!(
+            gaps,
+            vec![Gap {
+                start: t("2021-03-01-13:30"),
+                end: t("2021-03-01-15:00"),
+            }]
+        );
+
+        let gaps = find(&hours, &data, Duration::zero(), t("2021-03-01-16:00"));
+        assert_eq!(gaps.len(), 2);
+        assert_eq!(gaps[0].length(), Duration::minutes(3));
+
+        // Nothing tracked yet
+        let gaps = find(&hours, &[], Duration::zero(), t("2021-03-01-09:30"));
+        assert_eq!(
+            gaps,
+            vec![Gap {
+                start: t("2021-03-01-08:00"),
+                end: t("2021-03-01-09:30"),
+            }]
+        );
+    }
+}
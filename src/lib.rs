@@ -0,0 +1,21 @@
+//! Work time tracking: tasks, each with a log of entries, kept as TOML files in a data
+//! directory.
+//!
+//! The `dit` command line tool is built on this crate; other tools can use the same data
+//! directory through it without going through the command line:
+//!
+//! - [`models`]: tasks, log entries and the [`Repository`](models::Repository) trait
+//! - [`repository`]: the TOML files backend, and the one committing to git after each change
+//! - [`config`]: the `.config.toml` of a data directory
+//! - [`report`]: status, listings, budgets and balances, as data
+//! - [`utils::time`]: parsing timestamps and durations as the command line does
+//! - [`formats`]: importing and exporting other tools' formats
+
+pub mod charts;
+pub mod config;
+pub mod formats;
+pub mod gaps;
+pub mod models;
+pub mod report;
+pub mod repository;
+pub mod utils;
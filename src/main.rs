@@ -1,22 +1,33 @@
 use anyhow::{bail, Context, Result};
+use chrono::{Duration, NaiveDate};
 use clap::ArgMatches;
-use log::{debug, error};
+use log::{debug, error, LevelFilter};
+use std::path::Path;
 use std::process::exit;
 use std::str::FromStr;
 
-mod utils;
-use crate::utils::time::{now, parse_timestamp, Timestamp};
-
-mod models;
-
-mod repository;
-use crate::repository::toml::Repo;
+use dit::config::Config;
+use dit::models::Repository;
+use dit::repository::git::GitRepo;
+use dit::repository::toml::Repo;
+use dit::utils;
+use dit::utils::output::Pager;
+use dit::utils::template::Template;
+use dit::utils::time::{now, parse_day, parse_duration, parse_timestamp, Timestamp};
 
 mod commands;
-use crate::commands::Dit;
+use crate::commands::{Dit, ListOptions, StatusOptions, TEMPLATE_FIELDS};
 
 mod cli;
 
+mod completions;
+
+mod ui;
+
+#[cfg(test)]
+#[path = "utils/testing.rs"]
+mod testing;
+
 fn get_usize(cargs: &ArgMatches, name: &str) -> Result<usize> {
     let s = cargs.value_of(name).unwrap();
     usize::from_str_radix(s, 10).with_context(|| format!("Invalid value for '{}': {}", name, s))
@@ -31,8 +42,31 @@ fn get_timestamp(cargs: &ArgMatches, name: &str) -> Result<Option<Timestamp>> {
     }
 }
 
+fn get_duration(cargs: &ArgMatches, name: &str) -> Result<Option<Duration>> {
+    match cargs.value_of(name) {
+        Some(x) => parse_duration(x)
+            .with_context(|| format!("Invalid duration value for '{}': {}", name, x))
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+fn get_day(cargs: &ArgMatches, name: &str) -> Result<NaiveDate> {
+    let s = cargs.value_of(name).unwrap();
+    parse_day(s).with_context(|| format!("Invalid day for '{}': {}", name, s))
+}
+
 fn get_at(cargs: &ArgMatches) -> Result<Timestamp> {
-    get_timestamp(cargs, "at").map(|x| x.unwrap_or_else(|| now()))
+    get_timestamp(cargs, "at").map(|x| x.unwrap_or_else(now))
+}
+
+fn get_template(config: &Config, cargs: &ArgMatches, name: &str) -> Result<Option<Template>> {
+    match cargs.value_of(name) {
+        Some(x) => Template::parse(config.template(x)?, TEMPLATE_FIELDS)
+            .with_context(|| format!("Invalid value for '{}'", name))
+            .map(Some),
+        None => Ok(None),
+    }
 }
 
 fn get_single<T>(cargs: &ArgMatches, name: &str) -> Result<T, T::Err>
@@ -50,32 +84,82 @@ where
 }
 
 fn run(args: ArgMatches) -> Result<()> {
+    match args.subcommand() {
+        // Needs no data directory
+        Some(("completions", cargs)) => {
+            let script = completions::generate(cli::build(), get_single(cargs, "shell")?);
+            print!("{}", script);
+            return Ok(());
+        }
+        // The keys must not be mixed with warnings, e.g. about the index
+        Some(("__complete-keys", _)) => log::set_max_level(LevelFilter::Off),
+        // Rendered with every shell prompt, so the index is only loaded when the saved active task
+        // cannot be trusted
+        Some(("prompt", cargs)) => {
+            log::set_max_level(LevelFilter::Error);
+
+            let directory = utils::directory::resolve(args.value_of("directory"))?;
+            if let Some(active) = Repo::cached_active(&directory) {
+                let config = Config::load(&directory)?;
+                let template = get_template(&config, cargs, "format")?.unwrap();
+                return commands::print_prompt(&template, active.as_ref());
+            }
+        }
+        _ => {}
+    }
+
     let directory = utils::directory::resolve(args.value_of("directory"))?;
     debug!("Using data directory: {}", directory.display());
 
-    let repo = Repo::new(directory)?;
-    let dit = Dit::new(Box::new(repo));
+    let config = Config::load(&directory)?;
+    let repo: Box<dyn Repository> = match config.git.enabled {
+        true => Box::new(GitRepo::open(directory, &config.git.remote)?),
+        false => Box::new(Repo::new(directory)?),
+    };
+    let dit = Dit::new(repo, config);
+
+    // Listings may be long; the pager quits by itself when they fit on the screen
+    let _pager = match args.subcommand() {
+        Some(("status", cargs))
+        | Some(("list", cargs))
+        | Some(("budgets", cargs))
+        | Some(("balance", cargs))
+            if !cargs.is_present("no-pager") =>
+        {
+            Pager::start()?
+        }
+        _ => None,
+    };
 
     match args.subcommand() {
         Some(("new", cargs)) => dit.do_new(
             cargs.value_of("task").unwrap(),
             cargs.value_of("title"),
+            get_duration(cargs, "estimate")?,
             cargs.is_present("fetch"),
         ),
         Some(("work-on", cargs)) => {
-            let task = cargs.value_of("task").unwrap();
+            let task = match cargs.value_of("task") {
+                Some(t) => t.to_string(),
+                None => dit.pick_task()?,
+            };
             let now = get_at(&cargs)?;
 
             if cargs.is_present("new") {
-                dit.do_new(task, cargs.value_of("title"), cargs.is_present("fetch"))?;
+                dit.do_new(
+                    &task,
+                    cargs.value_of("title"),
+                    None,
+                    cargs.is_present("fetch"),
+                )?;
             }
 
-            dit.do_work_on(task, now)
+            dit.do_work_on(&task, now)
         }
         Some(("halt", cargs)) => {
             let now = get_at(&cargs)?;
 
-            dit.do_halt(now)
+            dit.do_halt(now, cargs.is_present("force"))
         }
         Some(("append", _)) => dit.do_append(),
         Some(("cancel", _)) => dit.do_cancel(),
@@ -85,37 +169,155 @@ fn run(args: ArgMatches) -> Result<()> {
             dit.do_work_on_by_index(now, get_usize(cargs, "index")?)
         }
         Some(("switch-to", cargs)) => {
-            let task = cargs.value_of("task").unwrap();
+            let task = match cargs.value_of("task") {
+                Some(t) => t.to_string(),
+                None => dit.pick_task()?,
+            };
             let now = get_at(&cargs)?;
 
             if cargs.is_present("new") {
-                dit.do_new(task, cargs.value_of("title"), cargs.is_present("fetch"))?;
+                dit.do_new(
+                    &task,
+                    cargs.value_of("title"),
+                    None,
+                    cargs.is_present("fetch"),
+                )?;
             }
 
-            dit.do_halt(now)?;
+            dit.do_halt(now, cargs.is_present("force"))?;
 
-            dit.do_work_on(task, now)
+            dit.do_work_on(&task, now)
         }
         Some(("switch-back", cargs)) => {
             let now = get_at(&cargs)?;
 
-            dit.do_halt(now)?;
+            dit.do_halt(now, cargs.is_present("force"))?;
 
             dit.do_work_on_by_index(now, get_usize(cargs, "index")?)
         }
+        Some(("fix-open", cargs)) => dit.do_fix_open(get_timestamp(cargs, "at")?),
+        Some(("note", cargs)) => dit.do_note(cargs.value_of("note").unwrap()),
+        Some(("estimate", cargs)) => dit.do_estimate(
+            cargs.value_of("task").unwrap(),
+            get_duration(cargs, "duration")?,
+            cargs.is_present("subtree"),
+        ),
+        Some(("budgets", cargs)) => dit.do_budgets(get_single(cargs, "format")?),
+        Some(("balance", cargs)) => dit.do_balance(
+            get_single(cargs, "mode")?,
+            get_single(cargs, "format")?,
+            get_timestamp(cargs, "after")?,
+            get_timestamp(cargs, "before")?,
+            cargs.is_present("include-archived"),
+        ),
+        Some(("chart", cargs)) => match cargs.subcommand() {
+            Some(("daily", ccargs)) => dit.do_chart_daily(
+                get_timestamp(ccargs, "after")?,
+                get_timestamp(ccargs, "before")?,
+                ccargs.is_present("include-archived"),
+            ),
+            Some(("heatmap", ccargs)) => dit.do_chart_heatmap(
+                match ccargs.value_of("year") {
+                    Some(_) => Some(get_single(ccargs, "year")?),
+                    None => None,
+                },
+                ccargs.is_present("include-archived"),
+            ),
+            Some((cmd, _)) => bail!("Unhandled chart: {}", cmd),
+            None => bail!("No chart provided"),
+        },
+        Some(("gaps", cargs)) => dit.do_gaps(
+            get_day(cargs, "day")?,
+            get_duration(cargs, "min")?.unwrap(),
+            cargs.is_present("assign"),
+        ),
+        Some(("archive", cargs)) => dit.do_archive(cargs.value_of("task").unwrap()),
+        Some(("rm", cargs)) => dit.do_rm(cargs.value_of("task").unwrap(), cargs.is_present("yes")),
+        Some(("doctor", cargs)) => dit.do_doctor(cargs.is_present("fix"), cargs.is_present("json")),
+        Some(("sync", _)) => dit.do_sync(),
+        Some(("ui", _)) => dit.do_ui(),
+        Some(("prompt", cargs)) => {
+            dit.do_prompt(&get_template(&dit.config, cargs, "format")?.unwrap())
+        }
+        Some(("__complete-keys", cargs)) => dit.do_complete_keys(cargs.value_of("prefix").unwrap()),
+        Some(("import", cargs)) => match cargs.subcommand() {
+            Some(("dit-v1", icargs)) => {
+                dit.do_import_dit_v1(Path::new(icargs.value_of("path").unwrap()))
+            }
+            Some(("timewarrior", icargs)) => {
+                let files: Vec<_> = icargs.values_of("files").unwrap().collect();
+                dit.do_import_timewarrior(&files)
+            }
+            Some(("org", icargs)) => dit.do_import_org(icargs.value_of("file").unwrap()),
+            Some(("json", icargs)) => dit.do_import_json(
+                icargs.value_of("file").unwrap(),
+                icargs.is_present("dry-run"),
+            ),
+            Some(("csv", icargs)) => dit.do_import_csv(
+                icargs.value_of("file").unwrap(),
+                icargs.value_of("mapping").unwrap(),
+            ),
+            Some(("timeclock", icargs)) => {
+                dit.do_import_timeclock(icargs.value_of("file").unwrap())
+            }
+            Some(("ical", icargs)) => dit.do_import_ical(
+                icargs.value_of("file").unwrap(),
+                icargs.is_present("dry-run"),
+                get_timestamp(icargs, "after")?,
+                get_timestamp(icargs, "before")?,
+            ),
+            Some((cmd, _)) => bail!("Unhandled import format: {}", cmd),
+            None => bail!("No import format provided"),
+        },
+        Some(("export", cargs)) => match cargs.subcommand() {
+            Some(("timewarrior", ecargs)) => dit.do_export_timewarrior(
+                get_timestamp(ecargs, "after")?,
+                get_timestamp(ecargs, "before")?,
+            ),
+            Some(("org", ecargs)) => dit.do_export_org(
+                ecargs.is_present("clocktable"),
+                get_timestamp(ecargs, "after")?,
+                get_timestamp(ecargs, "before")?,
+            ),
+            Some(("ical", ecargs)) => dit.do_export_ical(
+                get_timestamp(ecargs, "after")?,
+                get_timestamp(ecargs, "before")?,
+            ),
+            Some(("json", _)) => dit.do_export_json(),
+            Some(("csv", ecargs)) => dit.do_export_csv(
+                ecargs.value_of("mapping").unwrap(),
+                get_timestamp(ecargs, "after")?,
+                get_timestamp(ecargs, "before")?,
+            ),
+            Some(("timeclock", ecargs)) => dit.do_export_timeclock(
+                get_timestamp(ecargs, "after")?,
+                get_timestamp(ecargs, "before")?,
+            ),
+            Some((cmd, _)) => bail!("Unhandled export format: {}", cmd),
+            None => bail!("No export format provided"),
+        },
         Some(("status", cargs)) => dit.do_status(
-            cargs.is_present("short"),
+            &StatusOptions {
+                short: cargs.is_present("short"),
+                format: get_single(cargs, "format")?,
+                properties: get_many(cargs, "properties")?,
+                template: get_template(&dit.config, cargs, "template")?,
+            },
             cargs.is_present("rebuild-index"),
             get_usize(cargs, "limit")?,
-            get_many(cargs, "properties")?.as_slice(),
+            cargs.is_present("include-archived"),
         ),
         Some(("list", cargs)) => dit.do_list(
-            cargs.is_present("check"),
-            get_single(cargs, "mode")?,
-            get_single(cargs, "format")?,
-            get_many(cargs, "properties")?.as_slice(),
+            &ListOptions {
+                check: cargs.is_present("check"),
+                mode: get_single(cargs, "mode")?,
+                format: get_single(cargs, "format")?,
+                properties: get_many(cargs, "properties")?,
+                template: get_template(&dit.config, cargs, "template")?,
+            },
             get_timestamp(&cargs, "after")?,
             get_timestamp(&cargs, "before")?,
+            cargs.is_present("include-archived"),
         ),
         Some((cmd, _)) => bail!("Unhandled subcommand: {}", cmd),
         None => bail!("No subcommand provided"),
@@ -126,6 +328,7 @@ fn main() {
     let args = cli::parse();
 
     utils::logging::init(args.occurrences_of("verbose"));
+    utils::output::init();
 
     match run(args) {
         Err(err) => {
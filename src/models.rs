@@ -4,6 +4,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::HashMap;
 
 use crate::utils::time::{now, Timestamp};
 
@@ -16,6 +17,9 @@ pub struct Task {
 pub struct TaskData {
     pub title: String,
     #[serde(default)]
+    #[serde(with = "crate::utils::time::duration::optional")]
+    pub estimate: Option<Duration>,
+    #[serde(default)]
     pub log: Vec<LogEntry>,
 }
 
@@ -26,6 +30,8 @@ pub struct LogEntry {
     #[serde(default)]
     #[serde(with = "crate::utils::time::timestamp::optional")]
     pub end: Option<Timestamp>,
+    #[serde(default)]
+    pub note: Option<String>,
 }
 
 #[derive(Clone)]
@@ -33,6 +39,8 @@ pub struct ListItem {
     pub id: String,
     pub title: String,
     pub log_entry: LogEntry,
+    pub total_effort: Duration,
+    pub estimate: Option<Duration>,
 }
 
 pub struct StatusItem {
@@ -40,6 +48,46 @@ pub struct StatusItem {
     pub title: String,
     pub log_entry: LogEntry,
     pub total_effort: Duration,
+    pub estimate: Option<Duration>,
+}
+
+/// Time budget for a whole subtree of tasks, e.g. everything under `clients/acme`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Budget {
+    #[serde(with = "crate::utils::time::duration")]
+    pub estimate: Duration,
+}
+
+pub type Budgets = HashMap<String, Budget>;
+
+/// A problem found by `dit doctor`.
+#[derive(Serialize)]
+pub struct Finding {
+    pub check: Check,
+    pub id: Option<String>,
+    pub detail: String,
+    pub fixed: bool,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    Unparsable,
+    InvalidKey,
+    UnsortedLog,
+    EndBeforeStart,
+    ZeroLength,
+    MultipleOpen,
+    Overlap,
+    TooLong,
+    IndexMismatch,
+    StrayFile,
+}
+
+pub struct BudgetItem {
+    pub prefix: String,
+    pub estimate: Duration,
+    pub total_effort: Duration,
 }
 
 impl Task {
@@ -48,6 +96,7 @@ impl Task {
             id,
             data: TaskData {
                 title: String::new(),
+                estimate: None,
                 log: Vec::new(),
             },
         }
@@ -63,11 +112,33 @@ impl Task {
             false => Err(key.to_string()),
         }
     }
+
+    /// Whether the task is `prefix` itself or one of its descendants.
+    pub fn is_under(id: &str, prefix: &str) -> bool {
+        let id = id.trim_start_matches('/');
+        let prefix = prefix.trim_start_matches('/').trim_end_matches('/');
+
+        match id.strip_prefix(prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    pub fn total_effort(&self) -> Duration {
+        self.data
+            .log
+            .iter()
+            .fold(Duration::seconds(0), |a, x| a + x.effort())
+    }
 }
 
 impl LogEntry {
     pub fn new(start: Timestamp) -> LogEntry {
-        LogEntry { start, end: None }
+        LogEntry {
+            start,
+            end: None,
+            note: None,
+        }
     }
 
     pub fn is_open(&self) -> bool {
@@ -84,6 +155,13 @@ impl LogEntry {
             None => now() - self.start,
         }
     }
+
+    /// Whether both entries share some time; open entries last until now.
+    pub fn overlaps(&self, other: &LogEntry) -> bool {
+        let end = |x: &LogEntry| x.end.unwrap_or_else(now);
+
+        self.start < end(other) && other.start < end(self)
+    }
 }
 
 impl ListItem {
@@ -92,6 +170,8 @@ impl ListItem {
             id: task.id.clone(),
             title: task.data.title.clone(),
             log_entry: log_entry.clone(),
+            total_effort: task.total_effort(),
+            estimate: task.data.estimate,
         }
     }
 
@@ -106,9 +186,39 @@ impl ListItem {
     pub fn effort(&self) -> Duration {
         self.log_entry.effort()
     }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        remaining(self.estimate, self.total_effort)
+    }
+
+    pub fn progress(&self) -> Option<i64> {
+        progress(self.estimate, self.total_effort)
+    }
+}
+
+/// A list item for task `id` logged from `start` to `end`, as test data.
+#[cfg(test)]
+pub fn list_item(id: &str, start: &str, end: Option<&str>) -> ListItem {
+    let task = Task::new(id.to_string());
+    let entry = LogEntry {
+        start: crate::utils::time::parse_timestamp(start).unwrap(),
+        end: end.and_then(crate::utils::time::parse_timestamp),
+        note: None,
+    };
+    ListItem::new(&task, &entry)
 }
 
 impl StatusItem {
+    pub fn new(task: &Task, log_entry: &LogEntry) -> Self {
+        StatusItem {
+            id: task.id.clone(),
+            title: task.data.title.clone(),
+            log_entry: log_entry.clone(),
+            total_effort: task.total_effort(),
+            estimate: task.data.estimate,
+        }
+    }
+
     pub fn start(&self) -> Timestamp {
         self.log_entry.start
     }
@@ -120,11 +230,84 @@ impl StatusItem {
     pub fn effort(&self) -> Duration {
         self.log_entry.effort()
     }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        remaining(self.estimate, self.total_effort)
+    }
+
+    pub fn progress(&self) -> Option<i64> {
+        progress(self.estimate, self.total_effort)
+    }
+}
+
+/// Tracked time against the expected working time for a period, e.g. a day or a week.
+pub struct BalanceItem {
+    pub period: String,
+    pub target: Duration,
+    pub tracked: Duration,
+    pub balance: Duration,
+}
+
+impl Finding {
+    pub fn new(check: Check, id: Option<&str>, detail: &str) -> Self {
+        Finding {
+            check,
+            id: id.map(|x| x.to_string()),
+            detail: detail.to_string(),
+            fixed: false,
+        }
+    }
+}
+
+impl Check {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Check::Unparsable => "unparsable",
+            Check::InvalidKey => "invalid-key",
+            Check::UnsortedLog => "unsorted-log",
+            Check::EndBeforeStart => "end-before-start",
+            Check::ZeroLength => "zero-length",
+            Check::MultipleOpen => "multiple-open",
+            Check::Overlap => "overlap",
+            Check::TooLong => "too-long",
+            Check::IndexMismatch => "index-mismatch",
+            Check::StrayFile => "stray-file",
+        }
+    }
+}
+
+impl BudgetItem {
+    pub fn remaining(&self) -> Duration {
+        self.estimate - self.total_effort
+    }
+
+    pub fn progress(&self) -> Option<i64> {
+        progress(Some(self.estimate), self.total_effort)
+    }
+}
+
+impl BalanceItem {
+    pub fn difference(&self) -> Duration {
+        self.tracked - self.target
+    }
+}
+
+fn remaining(estimate: Option<Duration>, total_effort: Duration) -> Option<Duration> {
+    estimate.map(|e| e - total_effort)
+}
+
+/// Percentage of the estimate that has already been spent.
+fn progress(estimate: Option<Duration>, total_effort: Duration) -> Option<i64> {
+    match estimate.map(|e| e.num_seconds()) {
+        Some(e) if e > 0 => Some(total_effort.num_seconds() * 100 / e),
+        _ => None,
+    }
 }
 
 pub trait Repository {
     fn resolve_key(&self, key: &str) -> String;
     fn exists(&self, id: &String) -> bool;
+    fn list_ids(&self) -> Result<Vec<String>>;
     fn save(&self, task: &Task) -> Result<()>;
     fn load(&self, id: &String) -> Result<Task>;
     fn clock_in(&self, id: &String, now: Timestamp) -> Result<()>;
@@ -136,6 +319,14 @@ pub trait Repository {
     fn get_status(&self, limit: usize) -> Vec<StatusItem>;
     fn get_listing(&self, after: Option<Timestamp>, before: Option<Timestamp>) -> Result<Vec<ListItem>>;
     fn rebuild_index(&self) -> Result<()>;
+    fn refresh(&self) -> Result<()>;
+    fn load_budgets(&self) -> Result<Budgets>;
+    fn save_budgets(&self, budgets: &Budgets) -> Result<()>;
+    fn sync(&self) -> Result<()>;
+    fn check_files(&self, fix: bool) -> Result<Vec<Finding>>;
+    fn archive(&self, id: &str) -> Result<()>;
+    fn remove(&self, id: &str) -> Result<()>;
+    fn load_archived(&self) -> Result<Vec<Task>>;
 }
 
 impl Ord for LogEntry {
@@ -209,4 +400,16 @@ mod tests {
         assert_invalid_key!("foo.bar");
         assert_invalid_key!(".foo/bar");
     }
+
+    #[test]
+    fn test_is_under() {
+        assert!(Task::is_under("foo", "foo"));
+        assert!(Task::is_under("foo/bar", "foo"));
+        assert!(Task::is_under("/foo/bar", "foo/"));
+        assert!(Task::is_under("foo/bar/baz", "/foo/bar"));
+
+        assert!(!Task::is_under("foobar", "foo"));
+        assert!(!Task::is_under("foo", "foo/bar"));
+        assert!(!Task::is_under("bar/foo", "foo"));
+    }
 }
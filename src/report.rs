@@ -0,0 +1,458 @@
+//! Queries over the repository that return data rather than print it, for the `dit` commands and
+//! for other tools built on the same data.
+
+use anyhow::{bail, Result};
+use chrono::{Date, Datelike, Duration, FixedOffset, NaiveDate};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::config::Schedule;
+use crate::models::{
+    BalanceItem, BudgetItem, Check, Finding, ListItem, LogEntry, Repository, StatusItem, Task,
+};
+use crate::utils::nice::Nice;
+use crate::utils::time::Timestamp;
+
+pub enum BalanceMode {
+    Daily,
+    Weekly,
+}
+
+/// The latest entry of each task, most recent first; with `limit`, only that many tasks.
+pub fn status(
+    repo: &dyn Repository,
+    limit: usize,
+    include_archived: bool,
+) -> Result<Vec<StatusItem>> {
+    let mut status = repo.get_status(limit);
+
+    if include_archived {
+        for t in repo.load_archived()? {
+            if let Some(e) = t.data.log.last() {
+                status.push(StatusItem::new(&t, e));
+            }
+        }
+        status.sort_unstable_by(|x, y| y.log_entry.cmp(&x.log_entry));
+        if limit > 0 {
+            status.truncate(limit);
+        }
+    }
+    Ok(status)
+}
+
+/// Like `Repository::get_listing`, adding the entries of archived tasks if asked to.
+pub fn listing(
+    repo: &dyn Repository,
+    after: Option<Timestamp>,
+    before: Option<Timestamp>,
+    include_archived: bool,
+) -> Result<Vec<ListItem>> {
+    let mut items = repo.get_listing(after, before)?;
+
+    if include_archived {
+        let is_after = |x| after.map(|a| x >= a).unwrap_or(true);
+        let is_before = |x| before.map(|b| x <= b).unwrap_or(true);
+
+        for t in repo.load_archived()? {
+            items.extend(
+                t.data
+                    .log
+                    .iter()
+                    .filter(|e| is_after(e.start) && is_before(e.start))
+                    .map(|e| ListItem::new(&t, e)),
+            );
+        }
+        items.sort_unstable_by(|x, y| y.log_entry.cmp(&x.log_entry));
+    }
+    Ok(items)
+}
+
+/// Each budget with the effort of the tasks under it, archived ones included, by prefix.
+pub fn budgets(repo: &dyn Repository) -> Result<Vec<BudgetItem>> {
+    let status = status(repo, 0, true)?;
+
+    let mut items: Vec<BudgetItem> = repo
+        .load_budgets()?
+        .into_iter()
+        .map(|(prefix, b)| BudgetItem {
+            total_effort: total_effort_under(&status, &prefix),
+            estimate: b.estimate,
+            prefix,
+        })
+        .collect();
+
+    items.sort_unstable_by(|x, y| x.prefix.cmp(&y.prefix));
+
+    Ok(items)
+}
+
+/// The tracked time against the schedule for each day or week from `first` until `end`, with the
+/// running balance. The target of the day `end` falls on is only due in part, as it is not over.
+pub fn balance(
+    data: &[ListItem],
+    schedule: &Schedule,
+    first: NaiveDate,
+    end: Timestamp,
+    mode: BalanceMode,
+) -> Result<Vec<BalanceItem>> {
+    let mut tracked: HashMap<NaiveDate, Duration> = HashMap::new();
+    for (key, items) in group_by_day(data) {
+        let e = tracked
+            .entry(key.naive_local())
+            .or_insert_with(Duration::zero);
+        *e += total_effort(items);
+    }
+
+    let holidays = schedule.holidays()?;
+
+    let mut items: Vec<BalanceItem> = Vec::new();
+    let mut balance = Duration::zero();
+    let mut day = first;
+    let last = (end - Duration::nanoseconds(1)).naive_local().date();
+
+    while day <= last {
+        let target = schedule.target_until(day, end, &holidays);
+        let done = tracked.get(&day).cloned().unwrap_or_else(Duration::zero);
+
+        balance = balance + done - target;
+
+        let period = match mode {
+            BalanceMode::Daily => day.format("%a %F").to_string(),
+            BalanceMode::Weekly => {
+                let w = day.iso_week();
+                format!("{}-W{:02}", w.year(), w.week())
+            }
+        };
+
+        match items.last_mut() {
+            Some(i) if i.period == period => {
+                i.target += target;
+                i.tracked += done;
+                i.balance = balance;
+            }
+            _ => items.push(BalanceItem {
+                period,
+                target,
+                tracked: done,
+                balance,
+            }),
+        }
+
+        match day.succ_opt() {
+            Some(next) => day = next,
+            None => break,
+        }
+    }
+
+    Ok(items)
+}
+
+/// Audits the whole repository, fixing what can be fixed safely when `fix` is set; an entry still
+/// open counts as ending at `now`. The findings come by check, then by task.
+pub fn audit(
+    repo: &dyn Repository,
+    schedule: &Schedule,
+    fix: bool,
+    now: Timestamp,
+) -> Result<Vec<Finding>> {
+    let mut findings = repo.check_files(fix)?;
+
+    let broken: Vec<&String> = findings
+        .iter()
+        .filter(|f| f.check == Check::Unparsable)
+        .filter_map(|f| f.id.as_ref())
+        .collect();
+
+    let mut tasks = Vec::new();
+    for id in repo.list_ids()? {
+        if !broken.contains(&&id) {
+            tasks.push(repo.load(&id)?);
+        }
+    }
+
+    for task in tasks.iter_mut() {
+        let mut changed = false;
+
+        for e in task.data.log.iter() {
+            if let Some(end) = e.end {
+                if end < e.start {
+                    findings.push(Finding::new(
+                        Check::EndBeforeStart,
+                        Some(&task.id),
+                        &format!("{} | {}", e.start.nice(), end.nice()),
+                    ));
+                }
+            }
+        }
+
+        let zero = task
+            .data
+            .log
+            .iter()
+            .filter(|e| e.end == Some(e.start))
+            .count();
+        for e in task.data.log.iter().filter(|e| e.end == Some(e.start)) {
+            let mut f = Finding::new(Check::ZeroLength, Some(&task.id), &e.start.nice());
+            f.fixed = fix;
+            findings.push(f);
+        }
+        if fix && zero > 0 {
+            task.data.log.retain(|e| e.end != Some(e.start));
+            changed = true;
+        }
+
+        // Not fixed, as only the user knows when they stopped working
+        for e in task.data.log.iter() {
+            let end = e.end.unwrap_or(now);
+            if schedule.is_too_long(e.start, end) {
+                findings.push(Finding::new(
+                    Check::TooLong,
+                    Some(&task.id),
+                    &format!(
+                        "{} lasts {}, more than the maximum of {}",
+                        e.start.nice(),
+                        (end - e.start).nice(),
+                        schedule.max_entry.nice()
+                    ),
+                ));
+            }
+        }
+
+        // Open entries followed by others were most likely never halted
+        let n = task.data.log.len();
+        for i in 0..n.saturating_sub(1) {
+            if task.data.log[i].is_open() {
+                let next = task.data.log[i + 1].start;
+                let mut f = Finding::new(
+                    Check::MultipleOpen,
+                    Some(&task.id),
+                    &format!(
+                        "{} is open, but not the last entry",
+                        task.data.log[i].start.nice()
+                    ),
+                );
+                if fix {
+                    task.data.log[i].end = Some(next);
+                    f.fixed = true;
+                    changed = true;
+                }
+                findings.push(f);
+            }
+        }
+
+        if changed {
+            repo.save(task)?;
+        }
+    }
+
+    let open: Vec<&str> = tasks
+        .iter()
+        .filter(|t| t.data.log.last().map(|e| e.is_open()).unwrap_or(false))
+        .map(|t| t.id.as_str())
+        .collect();
+    if open.len() > 1 {
+        findings.push(Finding::new(
+            Check::MultipleOpen,
+            None,
+            &format!("more than one task is active: {}", open.join(", ")),
+        ));
+    }
+
+    // Open entries are still running, so they end now
+    let mut entries: Vec<(&str, &LogEntry, Timestamp)> = tasks
+        .iter()
+        .flat_map(|t| {
+            t.data
+                .log
+                .iter()
+                .map(move |e| (t.id.as_str(), e, e.end.unwrap_or(now)))
+        })
+        .collect();
+    entries.sort_by(|x, y| x.1.cmp(y.1));
+
+    // Compare each entry with the one reaching furthest so far, to catch long ones too
+    let mut furthest: Option<(&str, &LogEntry, Timestamp)> = None;
+    for (id, e, end) in entries {
+        if let Some((fid, f, fend)) = furthest {
+            if fend > e.start {
+                findings.push(Finding::new(
+                    Check::Overlap,
+                    Some(id),
+                    &format!("{} overlaps {} of {}", e.start.nice(), f.start.nice(), fid),
+                ));
+            }
+        }
+        if furthest.map(|(_, _, fend)| end > fend).unwrap_or(true) {
+            furthest = Some((id, e, end));
+        }
+    }
+
+    findings.sort_by(|x, y| (x.check, &x.id).cmp(&(y.check, &y.id)));
+    Ok(findings)
+}
+
+fn total_effort_under(status: &[StatusItem], prefix: &str) -> Duration {
+    status
+        .iter()
+        .filter(|x| Task::is_under(&x.id, prefix))
+        .fold(Duration::seconds(0), |a, x| a + x.total_effort)
+}
+
+pub fn total_effort(x: &[ListItem]) -> Duration {
+    x.iter().fold(Duration::seconds(0), |a, x| a + x.effort())
+}
+
+// based on: https://stackoverflow.com/a/50392400
+pub fn group_by_day(x: &[ListItem]) -> impl Iterator<Item = (Date<FixedOffset>, &[ListItem])> {
+    group_by(x, |z| z.log_entry.start.date())
+}
+
+/// Groups by ISO week, e.g. `2021-W09`.
+pub fn group_by_week(x: &[ListItem]) -> impl Iterator<Item = (String, &[ListItem])> {
+    group_by(x, |z| {
+        let w = z.log_entry.start.iso_week();
+        format!("{}-W{:02}", w.year(), w.week())
+    })
+}
+
+/// Splits sorted items into runs sharing the same key.
+fn group_by<K, F>(x: &[ListItem], key: F) -> impl Iterator<Item = (K, &[ListItem])>
+where
+    K: PartialEq,
+    F: Fn(&ListItem) -> K,
+{
+    let mut slice_start = 0;
+
+    (1..x.len() + 1).flat_map(move |i| {
+        let k = key(&x[i - 1]);
+
+        if i == x.len() || k != key(&x[i]) {
+            let start = slice_start;
+            slice_start = i;
+            Some((k, &x[start..i]))
+        } else {
+            None
+        }
+    })
+}
+
+impl FromStr for BalanceMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            _ => bail!("Invalid balance mode: {}", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{audit, balance, budgets, group_by_week, BalanceMode};
+    use crate::config::Schedule;
+    use crate::models::{list_item, Budget, Check, LogEntry, Repository, Task};
+    use crate::repository::toml::Repo;
+    use crate::utils::testing::TempDir;
+    use crate::utils::time::parse_timestamp;
+    use chrono::{Duration, NaiveDate};
+
+    #[test]
+    fn test_balance() {
+        // Most recent first, as listed
+        let data = vec![
+            list_item("acme/api", "2021-03-08-09:00", Some("2021-03-08-17:00")),
+            list_item("acme/api", "2021-03-02-09:00", Some("2021-03-02-19:00")),
+            list_item("acme/api", "2021-03-01-09:00", Some("2021-03-01-15:00")),
+        ];
+
+        let weeks: Vec<String> = group_by_week(&data).map(|(k, _)| k).collect();
+        assert_eq!(weeks, vec!["2021-W10", "2021-W09"]);
+
+        let first = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+        let end = parse_timestamp("2021-03-09-00:00").unwrap();
+        let items = balance(&data, &Schedule::default(), first, end, BalanceMode::Weekly).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].period, "2021-W09");
+        assert_eq!(items[0].target, Duration::hours(40));
+        assert_eq!(items[0].tracked, Duration::hours(16));
+        assert_eq!(items[1].balance, Duration::hours(-24));
+
+        // Two of the eight working hours of Monday are over
+        let end = parse_timestamp("2021-03-08-10:00").unwrap();
+        let items = balance(&data, &Schedule::default(), first, end, BalanceMode::Daily).unwrap();
+
+        assert_eq!(items.len(), 8);
+        assert_eq!(items[7].target, Duration::hours(2));
+    }
+
+    #[test]
+    fn test_budgets() {
+        let tmp = TempDir::new("budgets");
+        let dir = tmp.path().to_path_buf();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        for (id, end) in &[("acme/api", "2021-03-01-11:00"), ("acme/web", "2021-03-01-10:00")] {
+            let mut task = Task::new(id.to_string());
+            task.data.log.push(LogEntry {
+                start: parse_timestamp("2021-03-01-09:00").unwrap(),
+                end: parse_timestamp(end),
+                note: None,
+            });
+            repo.save(&task).unwrap();
+        }
+        let mut b = repo.load_budgets().unwrap();
+        b.insert(
+            "acme".to_string(),
+            Budget {
+                estimate: Duration::hours(5),
+            },
+        );
+        repo.save_budgets(&b).unwrap();
+
+        // Archived work still counts against the budget
+        repo.archive("acme/web").unwrap();
+
+        let items = budgets(&repo).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].total_effort, Duration::hours(3));
+    }
+
+    #[test]
+    fn test_audit() {
+        let tmp = TempDir::new("audit");
+        let dir = tmp.path().to_path_buf();
+        let repo = Repo::new(dir.clone()).unwrap();
+
+        let mut task = Task::new("foo".to_string());
+        task.data.log.push(LogEntry::new(parse_timestamp("2021-03-01-09:00").unwrap()));
+        repo.save(&task).unwrap();
+
+        let mut task = Task::new("bar".to_string());
+        task.data.log.push(LogEntry {
+            start: parse_timestamp("2021-03-01-10:00").unwrap(),
+            end: parse_timestamp("2021-03-01-10:00"),
+            note: None,
+        });
+        task.data.log.push(LogEntry {
+            start: parse_timestamp("2021-03-01-11:00").unwrap(),
+            end: parse_timestamp("2021-03-01-12:00"),
+            note: None,
+        });
+        repo.save(&task).unwrap();
+
+        // Still running, so foo overlaps bar
+        let schedule = Schedule::default();
+        let now = parse_timestamp("2021-03-01-13:00").unwrap();
+        let findings = audit(&repo, &schedule, true, now).unwrap();
+        let checks: Vec<(Check, bool)> = findings.iter().map(|f| (f.check, f.fixed)).collect();
+        assert!(checks == vec![(Check::ZeroLength, true), (Check::Overlap, false)]);
+
+        let findings = audit(&repo, &schedule, false, now).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].id.as_deref(), Some("bar"));
+    }
+}
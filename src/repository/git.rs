@@ -0,0 +1,521 @@
+//! Keeps the data directory in its own git repository. Every change is committed, and `sync`
+//! merges with a remote, resolving conflicting task files by taking the union of their log
+//! entries. The index is never committed; it is rebuilt after merging instead.
+
+use anyhow::{bail, Context, Result};
+use git2::build::CheckoutBuilder;
+use git2::{Commit, ErrorCode, IndexAddOption, Signature};
+use log::{debug, info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Budgets, Finding, ListItem, LogEntry, Repository, StatusItem, Task, TaskData};
+use crate::repository::toml::Repo;
+use crate::utils::time::Timestamp;
+
+const GITIGNORE: &str = ".gitignore";
+/// The index, and the active task saved along with it.
+const IGNORED: &[&str] = &[".index.toml", ".current.toml"];
+const BUDGETS: &str = ".budgets.toml";
+
+pub struct GitRepo {
+    inner: Repo,
+    git: git2::Repository,
+    remote: String,
+}
+
+impl Repository for GitRepo {
+    fn resolve_key(&self, key: &str) -> String {
+        self.inner.resolve_key(key)
+    }
+
+    fn exists(&self, id: &String) -> bool {
+        self.inner.exists(id)
+    }
+
+    fn list_ids(&self) -> Result<Vec<String>> {
+        self.inner.list_ids()
+    }
+
+    fn save(&self, task: &Task) -> Result<()> {
+        let verb = match self.inner.exists(&task.id) {
+            true => "Update",
+            false => "Add",
+        };
+        self.inner.save(task)?;
+        self.commit(&format!("{}: {}", verb, task.id))
+    }
+
+    fn load(&self, id: &String) -> Result<Task> {
+        self.inner.load(id)
+    }
+
+    fn clock_in(&self, id: &String, now: Timestamp) -> Result<()> {
+        self.inner.clock_in(id, now)?;
+        self.commit(&format!("Clock in: {}", id))
+    }
+
+    fn clock_out(&self, id: &String, now: Timestamp) -> Result<()> {
+        self.inner.clock_out(id, now)?;
+        self.commit(&format!("Clock out: {}", id))
+    }
+
+    fn un_clock_in(&self, id: &String) -> Result<()> {
+        self.inner.un_clock_in(id)?;
+        self.commit(&format!("Undo clock in: {}", id))
+    }
+
+    fn un_clock_out(&self, id: &String) -> Result<()> {
+        self.inner.un_clock_out(id)?;
+        self.commit(&format!("Undo clock out: {}", id))
+    }
+
+    fn is_clocked_in(&self) -> Option<String> {
+        self.inner.is_clocked_in()
+    }
+
+    fn previous_task(&self, i: usize) -> Option<(String, LogEntry)> {
+        self.inner.previous_task(i)
+    }
+
+    fn get_status(&self, limit: usize) -> Vec<StatusItem> {
+        self.inner.get_status(limit)
+    }
+
+    fn get_listing(
+        &self,
+        after: Option<Timestamp>,
+        before: Option<Timestamp>,
+    ) -> Result<Vec<ListItem>> {
+        self.inner.get_listing(after, before)
+    }
+
+    fn rebuild_index(&self) -> Result<()> {
+        self.inner.rebuild_index()
+    }
+
+    fn refresh(&self) -> Result<()> {
+        self.inner.refresh()
+    }
+
+    fn load_budgets(&self) -> Result<Budgets> {
+        self.inner.load_budgets()
+    }
+
+    fn save_budgets(&self, budgets: &Budgets) -> Result<()> {
+        self.inner.save_budgets(budgets)?;
+        self.commit("Update budgets")
+    }
+
+    fn check_files(&self, fix: bool) -> Result<Vec<Finding>> {
+        let findings = self.inner.check_files(fix)?;
+        if fix {
+            self.commit("Fix problems found by doctor")?;
+        }
+        Ok(findings)
+    }
+
+    fn archive(&self, id: &str) -> Result<()> {
+        self.inner.archive(id)?;
+        self.commit(&format!("Archive: {}", id))
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.inner.remove(id)?;
+        self.commit(&format!("Remove: {}", id))
+    }
+
+    fn load_archived(&self) -> Result<Vec<Task>> {
+        self.inner.load_archived()
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.commit("Commit pending changes")?;
+
+        let branch = match self.git.head() {
+            Ok(h) => h.shorthand().context("Invalid branch name")?.to_string(),
+            Err(e) if is_unborn(&e) => bail!("Nothing to sync yet"),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut remote = self
+            .git
+            .find_remote(&self.remote)
+            .with_context(|| format!("Could not find git remote: {}", self.remote))?;
+
+        let tracking = format!("refs/remotes/{}/{}", self.remote, branch);
+        remote
+            .fetch(
+                &[&format!("refs/heads/{}:{}", branch, tracking)],
+                None,
+                None,
+            )
+            .with_context(|| format!("Could not fetch from: {}", self.remote))?;
+
+        match self.git.find_reference(&tracking) {
+            Ok(r) => self.merge(&branch, &r.peel_to_commit()?)?,
+            Err(e) if e.code() == ErrorCode::NotFound => debug!("Remote branch not found"),
+            Err(e) => return Err(e.into()),
+        }
+
+        let spec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
+        remote
+            .push(&[&spec], None)
+            .with_context(|| format!("Could not push to: {}", self.remote))
+    }
+}
+
+impl GitRepo {
+    /// Opens the git repository in the data directory, creating it if needed.
+    pub fn open(directory: PathBuf, remote: &str) -> Result<Self> {
+        let git = match git2::Repository::open(&directory) {
+            Ok(g) => g,
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                info!("Creating git repository in: {}", directory.display());
+                git2::Repository::init(&directory)?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let r = GitRepo {
+            inner: Repo::new(directory)?,
+            git,
+            remote: remote.to_string(),
+        };
+        r.ignore_index()?;
+
+        Ok(r)
+    }
+
+    /// Makes sure the index and the active task are neither committed nor tracked.
+    fn ignore_index(&self) -> Result<()> {
+        let workdir = self.git.workdir().context("The git repository is bare")?;
+        let path = workdir.join(GITIGNORE);
+
+        let s = match path.is_file() {
+            true => fs::read_to_string(&path)
+                .with_context(|| format!("Could not read file: {}", path.display()))?,
+            false => String::new(),
+        };
+        let missing: Vec<&str> = IGNORED
+            .iter()
+            .filter(|x| !s.lines().any(|l| l.trim() == **x))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            let mut s = s;
+            if !s.is_empty() && !s.ends_with('\n') {
+                s.push('\n');
+            }
+            for x in missing {
+                s.push_str(x);
+                s.push('\n');
+            }
+            fs::write(&path, s)
+                .with_context(|| format!("Could not write to file: {}", path.display()))?;
+        }
+
+        let mut index = self.git.index()?;
+        for x in IGNORED {
+            if index.get_path(Path::new(x), 0).is_some() {
+                index.remove_path(Path::new(x))?;
+                index.write()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn signature(&self) -> Result<Signature<'static>> {
+        match self.git.signature() {
+            Ok(s) => Ok(s),
+            Err(_) => Ok(Signature::now("dit", "dit@localhost")?),
+        }
+    }
+
+    /// Commits all changes in the data directory, if there are any.
+    fn commit(&self, message: &str) -> Result<()> {
+        let mut index = self.git.index()?;
+        index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"].iter(), None)?;
+        index.write()?;
+
+        let tree_id = index.write_tree()?;
+
+        let parent = match self.git.head() {
+            Ok(h) => Some(h.peel_to_commit()?),
+            Err(e) if is_unborn(&e) => None,
+            Err(e) => return Err(e.into()),
+        };
+        if parent
+            .as_ref()
+            .map(|p| p.tree_id() == tree_id)
+            .unwrap_or(false)
+        {
+            return Ok(());
+        }
+
+        let tree = self.git.find_tree(tree_id)?;
+        let signature = self.signature()?;
+        let parents: Vec<&Commit> = parent.iter().collect();
+
+        self.git
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .context("Could not commit")?;
+
+        debug!("Committed: {}", message);
+        Ok(())
+    }
+
+    fn merge(&self, branch: &str, theirs: &Commit) -> Result<()> {
+        let annotated = self.git.find_annotated_commit(theirs.id())?;
+        let (analysis, _) = self.git.merge_analysis(&[&annotated])?;
+
+        if analysis.is_up_to_date() {
+            debug!("Already up to date");
+            return Ok(());
+        }
+
+        if analysis.is_fast_forward() {
+            debug!("Fast-forwarding to: {}", theirs.id());
+            self.git
+                .find_reference(&format!("refs/heads/{}", branch))?
+                .set_target(theirs.id(), "dit sync: fast-forward")?;
+        } else {
+            let ours = self.git.head()?.peel_to_commit()?;
+            let mut index = self.git.merge_commits(&ours, theirs, None)?;
+
+            let conflicts = index.conflicts()?.collect::<Result<Vec<_>, _>>()?;
+            for c in conflicts {
+                let entry = match c.our.as_ref().or(c.their.as_ref()) {
+                    Some(e) => e,
+                    None => continue,
+                };
+                let path = String::from_utf8_lossy(&entry.path).to_string();
+
+                let merged = self.merge_file(
+                    &path,
+                    self.blob(c.our.as_ref())?,
+                    self.blob(c.their.as_ref())?,
+                )?;
+
+                // The merged index belongs to no repository, so the blob is written here
+                let id = self.git.blob(&merged)?;
+
+                index.remove_path(Path::new(&path))?;
+                index.add(&git2::IndexEntry {
+                    ctime: entry.ctime,
+                    mtime: entry.mtime,
+                    dev: entry.dev,
+                    ino: entry.ino,
+                    mode: entry.mode,
+                    uid: entry.uid,
+                    gid: entry.gid,
+                    file_size: merged.len() as u32,
+                    id,
+                    flags: 0,
+                    flags_extended: 0,
+                    path: entry.path.clone(),
+                })?;
+            }
+
+            let tree = self.git.find_tree(index.write_tree_to(&self.git)?)?;
+            let signature = self.signature()?;
+
+            self.git.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &format!("Merge {}/{}", self.remote, branch),
+                &tree,
+                &[&ours, theirs],
+            )?;
+        }
+
+        self.git
+            .checkout_head(Some(CheckoutBuilder::new().force()))
+            .context("Could not update the data directory")?;
+
+        self.inner.rebuild_index()
+    }
+
+    fn blob(&self, entry: Option<&git2::IndexEntry>) -> Result<Option<Vec<u8>>> {
+        match entry {
+            Some(e) => Ok(Some(self.git.find_blob(e.id)?.content().to_vec())),
+            None => Ok(None),
+        }
+    }
+
+    /// Resolves a file changed on both sides: tasks and budgets are merged, anything else is
+    /// kept as it is locally.
+    fn merge_file(
+        &self,
+        path: &str,
+        ours: Option<Vec<u8>>,
+        theirs: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let (ours, theirs) = match (ours, theirs) {
+            (Some(o), Some(t)) => (o, t),
+            (Some(x), None) | (None, Some(x)) => return Ok(x),
+            (None, None) => return Ok(Vec::new()),
+        };
+
+        let parse = |x: &[u8]| String::from_utf8_lossy(x).to_string();
+
+        let name = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let merged = if name == BUDGETS {
+            let mut b: Budgets = toml::from_str(&parse(&ours))?;
+            let t: Budgets = toml::from_str(&parse(&theirs))?;
+            for (k, v) in t {
+                b.entry(k).or_insert(v);
+            }
+            toml::to_string_pretty(&b)?
+        } else if name.ends_with(".toml") && !name.starts_with('.') {
+            let o: TaskData = toml::from_str(&parse(&ours))
+                .with_context(|| format!("Could not parse local version of: {}", path))?;
+            let t: TaskData = toml::from_str(&parse(&theirs))
+                .with_context(|| format!("Could not parse remote version of: {}", path))?;
+            toml::to_string_pretty(&merge_task_data(o, t))?
+        } else {
+            warn!("Conflicting changes to {}; keeping the local version", path);
+            return Ok(ours);
+        };
+
+        debug!("Merged: {}", path);
+        Ok(merged.into_bytes())
+    }
+}
+
+/// Merges two versions of a task. The log entries are united; for entries starting at the same
+/// time, the closed one wins, and otherwise the local one. The rest is kept as it is locally,
+/// unless only the remote version has it.
+pub fn merge_task_data(ours: TaskData, theirs: TaskData) -> TaskData {
+    let mut log = ours.log;
+
+    for e in theirs.log {
+        match log.iter_mut().find(|x| x.start == e.start) {
+            Some(x) if x.is_open() && e.is_closed() => *x = e,
+            Some(_) => {}
+            None => log.push(e),
+        }
+    }
+    log.sort();
+
+    TaskData {
+        title: ours.title,
+        estimate: ours.estimate.or(theirs.estimate),
+        log,
+    }
+}
+
+fn is_unborn(e: &git2::Error) -> bool {
+    e.code() == ErrorCode::UnbornBranch || e.code() == ErrorCode::NotFound
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{DateTime, Duration};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{merge_task_data, GitRepo, IGNORED};
+    use crate::models::{LogEntry, Repository, Task, TaskData};
+    use crate::utils::testing::TempDir;
+
+    fn entry(start: &str, minutes: Option<i64>) -> LogEntry {
+        let start = DateTime::parse_from_str(start, "%F %T %z").unwrap();
+        LogEntry {
+            start,
+            end: minutes.map(|m| start + Duration::minutes(m)),
+            note: None,
+        }
+    }
+
+    fn clone(dir: PathBuf, bare: &Path) -> GitRepo {
+        fs::create_dir_all(&dir).unwrap();
+        let r = GitRepo::open(dir, "origin").unwrap();
+        r.git.remote("origin", bare.to_str().unwrap()).unwrap();
+        r
+    }
+
+    #[test]
+    fn test_merge_task_data() {
+        let ours = TaskData {
+            title: "Ours".to_string(),
+            estimate: None,
+            log: vec![
+                entry("2026-10-16 09:00:00 +0000", Some(60)),
+                entry("2026-10-16 13:00:00 +0000", None),
+            ],
+        };
+        let theirs = TaskData {
+            title: "Theirs".to_string(),
+            estimate: Some(Duration::hours(2)),
+            log: vec![
+                entry("2026-10-16 11:00:00 +0000", Some(30)),
+                entry("2026-10-16 13:00:00 +0000", Some(30)),
+            ],
+        };
+
+        let m = merge_task_data(ours, theirs);
+
+        assert_eq!(m.title, "Ours");
+        assert_eq!(m.estimate, Some(Duration::hours(2)));
+        assert_eq!(m.log.len(), 3);
+        assert!(m.log.iter().all(|e| e.is_closed()));
+    }
+
+    #[test]
+    fn test_sync() {
+        let tmp = TempDir::new("sync");
+        let root = tmp.path();
+        let bare = root.join("remote.git");
+        git2::Repository::init_bare(&bare).unwrap();
+
+        let a = clone(root.join("a"), &bare);
+        let b = clone(root.join("b"), &bare);
+
+        let mut task = Task::new("foo".to_string());
+        task.data.title = "Foo".to_string();
+        task.data
+            .log
+            .push(entry("2026-10-16 09:00:00 +0000", Some(60)));
+        a.save(&task).unwrap();
+        a.sync().unwrap();
+
+        let mut task = Task::new("foo".to_string());
+        task.data.title = "Foo".to_string();
+        task.data
+            .log
+            .push(entry("2026-10-16 11:00:00 +0000", Some(60)));
+        b.save(&task).unwrap();
+        b.sync().unwrap();
+
+        a.sync().unwrap();
+
+        for r in [&a, &b].iter() {
+            let task = r.load(&"foo".to_string()).unwrap();
+            assert_eq!(task.data.log.len(), 2);
+            assert_eq!(r.get_status(0).len(), 1);
+            for ignored in IGNORED {
+                assert!(r
+                    .git
+                    .index()
+                    .unwrap()
+                    .get_path(Path::new(ignored), 0)
+                    .is_none());
+            }
+        }
+    }
+}
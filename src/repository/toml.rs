@@ -1,32 +1,92 @@
 use anyhow::{bail, Context, Result};
 use chrono::Duration;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 use toml;
 use walkdir::WalkDir;
 
-use crate::models::{ListItem, LogEntry, Repository, StatusItem, Task, TaskData};
+use crate::models::{
+    Budgets, Check, Finding, ListItem, LogEntry, Repository, StatusItem, Task, TaskData,
+};
 use crate::utils::directory;
 use crate::utils::time::Timestamp;
 
+/// Where archived tasks are kept, under their usual keys. Being hidden, it is neither walked nor
+/// indexed.
+const ARCHIVE: &str = ".archive";
+
+/// Bumped whenever the layout of the index changes; indexes of other versions are rebuilt.
+const INDEX_VERSION: u32 = 3;
+
+/// The active task, saved along with the index so that `dit prompt` needs to read nothing else.
+const CURRENT: &str = ".current";
+
 pub struct Repo {
     directory: PathBuf,
     index: RefCell<Index>,
+    stamps: RefCell<Stamps>,
 }
 
 type Index = HashMap<String, IndexEntry>;
 
+type Stamps = HashMap<String, Stamp>;
+
+/// What a task file, or the data directory, looked like when it was last indexed.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct Stamp {
+    modified: i64,
+    size: u64,
+}
+
+#[derive(Deserialize)]
+struct IndexFile {
+    version: u32,
+    #[serde(default)]
+    stamps: Stamps,
+    #[serde(default)]
+    tasks: Index,
+}
+
+#[derive(Serialize)]
+struct IndexFileRef<'a> {
+    version: u32,
+    stamps: &'a Stamps,
+    tasks: &'a Index,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CurrentFile {
+    version: u32,
+    active: Option<CurrentTask>,
+    /// Changes when task files are added or removed, e.g. one with an open entry.
+    #[serde(default)]
+    directory: Option<Stamp>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CurrentTask {
+    id: String,
+    #[serde(flatten)]
+    entry: IndexEntry,
+    stamp: Stamp,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 struct IndexEntry {
     title: String,
     #[serde(flatten)]
     log_entry: LogEntry,
+    /// Effort of the closed log entries; an open one is added when read, as it keeps growing.
     #[serde(with = "crate::utils::time::duration")]
     total_effort: Duration,
+    #[serde(default)]
+    #[serde(with = "crate::utils::time::duration::optional")]
+    estimate: Option<Duration>,
 }
 
 impl IndexEntry {
@@ -34,16 +94,35 @@ impl IndexEntry {
         IndexEntry {
             title: task.data.title.clone(),
             log_entry: entry.clone(),
-            total_effort: task.total_effort(),
+            total_effort: task
+                .data
+                .log
+                .iter()
+                .filter(|e| !e.is_open())
+                .fold(Duration::seconds(0), |a, x| a + x.effort()),
+            estimate: task.data.estimate,
         }
     }
 
+    fn same_as(&self, other: &IndexEntry) -> bool {
+        self.title == other.title
+            && self.log_entry.start == other.log_entry.start
+            && self.log_entry.end == other.log_entry.end
+            && self.total_effort == other.total_effort
+            && self.estimate == other.estimate
+    }
+
     fn to_status(&self, id: &String) -> StatusItem {
+        let total_effort = match self.log_entry.is_open() {
+            true => self.total_effort + self.log_entry.effort(),
+            false => self.total_effort,
+        };
         StatusItem {
             id: id.clone(),
             title: self.title.clone(),
             log_entry: self.log_entry.clone(),
-            total_effort: self.total_effort.clone(),
+            total_effort,
+            estimate: self.estimate,
         }
     }
 }
@@ -57,12 +136,39 @@ impl Repository for Repo {
         self.path(&id).exists()
     }
 
+    fn list_ids(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+
+        for entry in self.walk() {
+            let p = entry
+                .with_context(|| {
+                    format!(
+                        "Could not complete traversal of: {}",
+                        &self.directory.display()
+                    )
+                })?
+                .into_path();
+
+            if !p.extension().map(|x| x.eq("toml")).unwrap_or(false) {
+                continue;
+            }
+
+            if !p.is_file() {
+                continue;
+            }
+
+            ids.push(self.id_from_full_path(&p)?);
+        }
+        Ok(ids)
+    }
+
     fn save(&self, task: &Task) -> Result<()> {
         debug!("Saving task: {}", task.id);
 
         write(&self.path(&task.id), &task.data)
             .with_context(|| format!("Could not save task: {}", task.id))?;
         self.update_index(&task);
+        self.update_stamp(&task.id)?;
         self.save_index()
     }
 
@@ -181,56 +287,33 @@ impl Repository for Repo {
     }
 
     fn rebuild_index(&self) -> Result<()> {
-        self.index.borrow_mut().clear();
-
-        for id in self.list_ids()? {
-            let t = self.load(&id)?;
-            self.update_index(&t);
-        }
+        let (index, stamps) = self.fresh_index()?;
+        *self.index.borrow_mut() = index;
+        *self.stamps.borrow_mut() = stamps;
         self.save_index()
     }
-}
 
-impl Repo {
-    pub fn new(directory: PathBuf) -> Result<Self> {
-        let index = Repo::load_index(&directory)?;
-        Repo::check_index(&index)?;
-        Ok(Repo {
-            directory,
-            index: RefCell::new(index),
-        })
+    fn refresh(&self) -> Result<()> {
+        self.refresh_index()
     }
 
-    fn path(&self, id: &str) -> PathBuf {
-        self.directory.join(id).with_extension("toml")
+    fn load_budgets(&self) -> Result<Budgets> {
+        let p = self.path(".budgets");
+        if p.is_file() {
+            read(&p)
+        } else {
+            Ok(Budgets::new())
+        }
     }
 
-    fn id_from_full_path(&self, path: &PathBuf) -> Result<String> {
-        let id = path
-            .strip_prefix(&self.directory)
-            .with_context(|| {
-                format!(
-                    "Given path is not a child of dit directory: {}",
-                    path.display()
-                )
-            })?
-            .display()
-            .to_string()
-            .strip_suffix(".toml")
-            .with_context(|| {
-                format!(
-                    "Given path does not have toml extension: {}",
-                    path.display()
-                )
-            })?
-            .to_string();
-        Ok(id)
+    fn save_budgets(&self, budgets: &Budgets) -> Result<()> {
+        write(&self.path(".budgets"), budgets).context("Could not save budgets")
     }
 
-    fn list_ids(&self) -> Result<Vec<String>> {
-        let mut ids = Vec::new();
+    fn check_files(&self, fix: bool) -> Result<Vec<Finding>> {
+        let mut findings = Vec::new();
 
-        for entry in WalkDir::new(&self.directory) {
+        for entry in self.walk() {
             let p = entry
                 .with_context(|| {
                     format!(
@@ -240,17 +323,214 @@ impl Repo {
                 })?
                 .into_path();
 
+            if !p.is_file() {
+                continue;
+            }
+
             if !p.extension().map(|x| x.eq("toml")).unwrap_or(false) {
+                let name = p.strip_prefix(&self.directory).unwrap_or(&p).display();
+                findings.push(Finding::new(
+                    Check::StrayFile,
+                    None,
+                    &format!("not a task file: {}", name),
+                ));
                 continue;
             }
 
-            if !p.is_file() {
+            let id = self.id_from_full_path(&p)?;
+
+            if Task::validate_key(&id).is_err() {
+                findings.push(Finding::new(
+                    Check::InvalidKey,
+                    Some(&id),
+                    "not a valid task key",
+                ));
+            }
+
+            // Read as is, since loading would sort the log
+            let data: TaskData = match read(&p) {
+                Ok(d) => d,
+                Err(e) => {
+                    findings.push(Finding::new(
+                        Check::Unparsable,
+                        Some(&id),
+                        &format!("{:#}", e),
+                    ));
+                    continue;
+                }
+            };
+            let mut task = Task::from_data(id, data);
+
+            if !task.data.log.windows(2).all(|w| w[0] <= w[1]) {
+                let mut f = Finding::new(
+                    Check::UnsortedLog,
+                    Some(&task.id),
+                    "log entries are not in chronological order",
+                );
+                if fix {
+                    task.data.log.sort();
+                    self.save(&task)?;
+                    f.fixed = true;
+                }
+                findings.push(f);
+            }
+        }
+
+        // The index was refreshed when the repository was opened, which trusts the stamps
+        let (expected, stamps) = self.fresh_index()?;
+
+        let mut mismatches = Vec::new();
+        for (id, e) in expected.iter() {
+            match self.index.borrow().get(id) {
+                Some(x) if x.same_as(e) => {}
+                Some(_) => mismatches.push((id.clone(), "outdated in the index")),
+                None => mismatches.push((id.clone(), "missing from the index")),
+            }
+        }
+        for id in self.index.borrow().keys() {
+            if !expected.contains_key(id) {
+                mismatches.push((id.clone(), "in the index, but has no readable log"));
+            }
+        }
+        mismatches.sort();
+
+        let fixed = fix && !mismatches.is_empty();
+        if fixed {
+            *self.index.borrow_mut() = expected;
+            *self.stamps.borrow_mut() = stamps;
+            self.save_index()?;
+        }
+        for (id, detail) in mismatches {
+            let mut f = Finding::new(Check::IndexMismatch, Some(&id), detail);
+            f.fixed = fixed;
+            findings.push(f);
+        }
+
+        Ok(findings)
+    }
+
+    fn archive(&self, id: &str) -> Result<()> {
+        debug!("Archiving task: {}", id);
+
+        let from = self.path(id);
+        let to = self.archive_path(id);
+        if to.exists() {
+            bail!("Task already archived: {}", id);
+        }
+
+        directory::ensure_exists(to.parent().unwrap())?;
+        fs::rename(&from, &to).with_context(|| format!("Could not archive task: {}", id))?;
+        self.forget(id)
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        debug!("Removing task: {}", id);
+
+        fs::remove_file(self.path(id)).with_context(|| format!("Could not remove task: {}", id))?;
+        self.forget(id)
+    }
+
+    fn load_archived(&self) -> Result<Vec<Task>> {
+        let archive = self.directory.join(ARCHIVE);
+        let mut tasks = Vec::new();
+
+        if !archive.is_dir() {
+            return Ok(tasks);
+        }
+
+        for entry in WalkDir::new(&archive) {
+            let p = entry
+                .with_context(|| format!("Could not complete traversal of: {}", archive.display()))?
+                .into_path();
+
+            if !p.is_file() || !p.extension().map(|x| x.eq("toml")).unwrap_or(false) {
                 continue;
             }
 
-            ids.push(self.id_from_full_path(&p)?);
+            let id = id_from_path(&archive, &p)?;
+            let mut data: TaskData =
+                read(&p).with_context(|| format!("Could not load archived task: {}", id))?;
+            data.log.sort();
+            tasks.push(Task::from_data(id, data));
         }
-        Ok(ids)
+        Ok(tasks)
+    }
+
+    fn sync(&self) -> Result<()> {
+        bail!("Syncing needs the git integration; set 'enabled = true' under '[git]' in the configuration")
+    }
+}
+
+impl Repo {
+    pub fn new(directory: PathBuf) -> Result<Self> {
+        let (index, stamps) = Repo::load_index(&directory);
+        let repo = Repo {
+            directory,
+            index: RefCell::new(index),
+            stamps: RefCell::new(stamps),
+        };
+        repo.refresh_index()?;
+        repo.check_index();
+        if Repo::cached_active(&repo.directory).is_none() {
+            repo.save_current()?;
+        }
+        Ok(repo)
+    }
+
+    /// Reads the active task saved along with the index, if any, without loading the index.
+    /// Returns `None` when that cannot be trusted: the file is missing, the active task changed
+    /// since, e.g. when its file was edited by hand, or task files were added or removed. Only
+    /// then the repository needs to be opened.
+    pub fn cached_active(directory: &Path) -> Option<Option<StatusItem>> {
+        let f = read::<CurrentFile>(&directory.join(CURRENT).with_extension("toml")).ok()?;
+        if f.version != INDEX_VERSION || f.directory != stamp(directory).ok() {
+            return None;
+        }
+
+        match f.active {
+            Some(c) => {
+                let repo_stamp = stamp(&directory.join(&c.id).with_extension("toml")).ok()?;
+                match repo_stamp == c.stamp {
+                    true => Some(Some(c.entry.to_status(&c.id))),
+                    false => None,
+                }
+            }
+            None => Some(None),
+        }
+    }
+
+    /// Walks the data directory, skipping hidden entries such as the index or a `.git` directory.
+    fn walk(&self) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> {
+        WalkDir::new(&self.directory)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !directory::is_hidden(e.file_name()))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.directory.join(id).with_extension("toml")
+    }
+
+    fn id_from_full_path(&self, path: &PathBuf) -> Result<String> {
+        id_from_path(&self.directory, path)
+    }
+
+    fn archive_path(&self, id: &str) -> PathBuf {
+        self.directory.join(ARCHIVE).join(id).with_extension("toml")
+    }
+
+    /// Drops a task whose file is gone from the index, and removes directories left empty.
+    fn forget(&self, id: &str) -> Result<()> {
+        self.index.borrow_mut().remove(id);
+        self.stamps.borrow_mut().remove(id);
+
+        let mut dir = self.path(id).parent().map(Path::to_path_buf);
+        while let Some(d) = dir {
+            if d == self.directory || fs::remove_dir(&d).is_err() {
+                break;
+            }
+            dir = d.parent().map(Path::to_path_buf);
+        }
+        self.save_index()
     }
 
     fn sorted_index(&self) -> Vec<(String, IndexEntry)> {
@@ -263,33 +543,153 @@ impl Repo {
         d
     }
 
-    fn load_index(directory: &Path) -> Result<Index> {
+    /// Loads the index, or starts a new one if it is missing, unreadable or from another version;
+    /// `refresh_index` then fills it in.
+    fn load_index(directory: &Path) -> (Index, Stamps) {
         trace!("Loading index");
 
         let s = directory.join(".index").with_extension("toml");
-        if s.is_file() {
-            read(&s)
-        } else {
+        if !s.is_file() {
             debug!("Index not found; using new, empty one");
-            Ok(Index::new())
+            return (Index::new(), Stamps::new());
+        }
+
+        match read::<IndexFile>(&s) {
+            Ok(f) if f.version == INDEX_VERSION => (f.tasks, f.stamps),
+            Ok(f) => {
+                debug!("Index has version {}; using new, empty one", f.version);
+                (Index::new(), Stamps::new())
+            }
+            Err(e) => {
+                debug!("Could not load index; using new, empty one: {:#}", e);
+                (Index::new(), Stamps::new())
+            }
         }
     }
 
-    fn check_index(index: &Index) -> Result<()> {
-        let c = index
-            .values()
-            .fold(0, |a, i| if i.log_entry.is_open() { a + 1 } else { a });
+    /// Re-indexes the tasks whose files changed since they were last indexed, and forgets the
+    /// ones whose files are gone.
+    fn refresh_index(&self) -> Result<()> {
+        let mut changed = false;
+        let mut seen = HashSet::new();
 
-        if c > 1 {
-            bail!("Index contains more than one active task; rebuild index?");
+        for id in self.list_ids()? {
+            let stamp = self.stamp(&id)?;
+
+            if self.stamps.borrow().get(&id) != Some(&stamp) {
+                debug!("Indexing: {}", id);
+
+                match self.load(&id) {
+                    Ok(t) => self.update_index(&t),
+                    Err(e) => {
+                        warn!("Not indexed: {:#}", e);
+                        self.index.borrow_mut().remove(&id);
+                    }
+                }
+                self.stamps.borrow_mut().insert(id.clone(), stamp);
+                changed = true;
+            }
+            seen.insert(id);
+        }
+
+        let gone: Vec<String> = self
+            .stamps
+            .borrow()
+            .keys()
+            .chain(self.index.borrow().keys())
+            .filter(|id| !seen.contains(*id))
+            .cloned()
+            .collect();
+
+        for id in gone {
+            debug!("Forgetting: {}", id);
+            self.stamps.borrow_mut().remove(&id);
+            self.index.borrow_mut().remove(&id);
+            changed = true;
+        }
+
+        match changed {
+            true => self.save_index(),
+            false => Ok(()),
+        }
+    }
+
+    fn check_index(&self) {
+        let active: Vec<String> = self
+            .index
+            .borrow()
+            .iter()
+            .filter(|(_, v)| v.log_entry.is_open())
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        if active.len() > 1 {
+            warn!("More than one task is active: {}", active.join(", "));
         }
-        Ok(())
     }
 
     fn save_index(&self) -> Result<()> {
         trace!("Saving index");
 
-        write(&self.path(".index"), &self.index).context("Could not save index")
+        let f = IndexFileRef {
+            version: INDEX_VERSION,
+            stamps: &self.stamps.borrow(),
+            tasks: &self.index.borrow(),
+        };
+        write(&self.path(".index"), &f).context("Could not save index")?;
+        self.save_current()
+    }
+
+    fn save_current(&self) -> Result<()> {
+        let active = match self
+            .sorted_index()
+            .into_iter()
+            .find(|(_, v)| v.log_entry.is_open())
+        {
+            Some((id, entry)) => Some(CurrentTask {
+                stamp: self.stamp(&id)?,
+                id,
+                entry,
+            }),
+            None => None,
+        };
+
+        let f = CurrentFile {
+            version: INDEX_VERSION,
+            active,
+            directory: Some(stamp(&self.directory)?),
+        };
+        write(&self.path(CURRENT), &f).context("Could not save active task")
+    }
+
+    fn stamp(&self, id: &str) -> Result<Stamp> {
+        stamp(&self.path(id))
+    }
+
+    /// Indexes every task from scratch, whatever the stamps say; tasks that cannot be loaded are
+    /// left out.
+    fn fresh_index(&self) -> Result<(Index, Stamps)> {
+        let mut index = Index::new();
+        let mut stamps = Stamps::new();
+
+        for id in self.list_ids()? {
+            match self.load(&id) {
+                Ok(t) => {
+                    if let Some(e) = t.data.log.last() {
+                        index.insert(id.clone(), IndexEntry::new(&t, e));
+                    }
+                }
+                Err(e) => warn!("Not indexed: {:#}", e),
+            }
+            stamps.insert(id.clone(), self.stamp(&id)?);
+        }
+        Ok((index, stamps))
+    }
+
+    fn update_stamp(&self, id: &str) -> Result<()> {
+        let stamp = self.stamp(id)?;
+        self.stamps.borrow_mut().insert(id.to_string(), stamp);
+        Ok(())
     }
 
     fn update_index(&self, task: &Task) {
@@ -303,13 +703,42 @@ impl Repo {
     }
 }
 
-impl Task {
-    fn total_effort(&self) -> Duration {
-        self.data
-            .log
-            .iter()
-            .fold(Duration::seconds(0), |a, x| a + x.effort())
-    }
+fn id_from_path(directory: &Path, path: &Path) -> Result<String> {
+    let id = path
+        .strip_prefix(directory)
+        .with_context(|| {
+            format!(
+                "Given path is not a child of dit directory: {}",
+                path.display()
+            )
+        })?
+        .display()
+        .to_string()
+        .strip_suffix(".toml")
+        .with_context(|| {
+            format!(
+                "Given path does not have toml extension: {}",
+                path.display()
+            )
+        })?
+        .to_string();
+    Ok(id)
+}
+
+fn stamp(p: &Path) -> Result<Stamp> {
+    let m = fs::metadata(p).with_context(|| format!("Could not read: {}", p.display()))?;
+
+    let modified = m
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0);
+
+    Ok(Stamp {
+        modified,
+        size: m.len(),
+    })
 }
 
 fn read<T: DeserializeOwned>(f: &Path) -> Result<T> {
@@ -329,3 +758,107 @@ fn write<T: Serialize>(f: &Path, d: &T) -> Result<()> {
     let s = toml::to_string_pretty(&d).context("Could not serialize object")?;
     fs::write(f, s).with_context(|| format!("Could not write to file: {}", f.display()))
 }
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::Repo;
+    use crate::models::{Check, LogEntry, Repository, Task};
+    use crate::utils::testing::TempDir;
+    use crate::utils::time::now;
+
+    #[test]
+    fn test_refresh_index() {
+        let tmp = TempDir::new("index");
+        let dir = tmp.path().to_path_buf();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        let mut task = Task::new("foo".to_string());
+        task.data.log.push(LogEntry::new(now()));
+        repo.save(&task).unwrap();
+
+        assert_eq!(repo.is_clocked_in(), Some("foo".to_string()));
+
+        // Closed behind dit's back
+        let path = dir.join("foo.toml");
+        let s = fs::read_to_string(&path).unwrap();
+        fs::write(&path, format!("{}end = '2026-10-16 10:30:00 +0000'\n", s)).unwrap();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        assert_eq!(repo.is_clocked_in(), None);
+
+        // Written by an older version of dit
+        fs::write(dir.join(".index.toml"), "[foo]\ntitle = 'Foo'\n").unwrap();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        assert_eq!(repo.get_status(0).len(), 1);
+
+        fs::remove_file(&path).unwrap();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        assert!(repo.get_status(0).is_empty());
+
+        // Nothing is active, until a task is added by hand; the pause lets the directory's
+        // modification time move on even on file systems with coarse timestamps
+        assert!(matches!(Repo::cached_active(&dir), Some(None)));
+        thread::sleep(Duration::from_millis(50));
+        fs::write(
+            dir.join("bar.toml"),
+            "title = 'Bar'\n[[log]]\nstart = '2026-10-16 09:00:00 +0000'\n",
+        )
+        .unwrap();
+        assert!(Repo::cached_active(&dir).is_none());
+        let repo = Repo::new(dir.clone()).unwrap();
+        assert_eq!(repo.is_clocked_in(), Some("bar".to_string()));
+        assert!(matches!(Repo::cached_active(&dir), Some(Some(_))));
+
+        // Edited by hand with the stamps left alone, so opening the repository keeps it
+        let index = dir.join(".index.toml");
+        let s = fs::read_to_string(&index).unwrap();
+        fs::write(&index, s.replace("title = 'Bar'", "title = 'Baz'")).unwrap();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        let findings = repo.check_files(false).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].check == Check::IndexMismatch && !findings[0].fixed);
+
+        assert!(repo.check_files(true).unwrap()[0].fixed);
+        assert!(repo.check_files(false).unwrap().is_empty());
+        assert_eq!(repo.get_status(0)[0].title, "Bar");
+    }
+
+    #[test]
+    fn test_archive_and_remove() {
+        let tmp = TempDir::new("archive");
+        let dir = tmp.path().to_path_buf();
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        for id in &["foo/bar", "baz"] {
+            let mut task = Task::new(id.to_string());
+            task.data.log.push(LogEntry::new(now()));
+            repo.save(&task).unwrap();
+        }
+
+        repo.archive("foo/bar").unwrap();
+        assert!(!repo.exists(&"foo/bar".to_string()));
+        assert!(!dir.join("foo").exists());
+        assert_eq!(repo.list_ids().unwrap(), vec!["baz".to_string()]);
+        assert_eq!(repo.load_archived().unwrap()[0].id, "foo/bar");
+        assert!(repo.archive("baz").is_ok());
+
+        let repo = Repo::new(dir.clone()).unwrap();
+        assert!(repo.get_status(0).is_empty());
+
+        let mut task = Task::new("qux".to_string());
+        task.data.log.push(LogEntry::new(now()));
+        repo.save(&task).unwrap();
+        repo.remove("qux").unwrap();
+        assert!(repo.list_ids().unwrap().is_empty());
+        assert!(repo.get_status(0).is_empty());
+        assert_eq!(repo.load_archived().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,439 @@
+//! Full-screen dashboard: the active task with a ticking timer, the most recent tasks, and the log
+//! entries of a day or a week. Every action goes through the same `Dit` operations as the CLI.
+
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use crossterm::cursor::Show;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use std::io::{self, Stdout};
+use std::mem;
+use std::panic;
+use std::time::Instant;
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use tui::{Frame, Terminal};
+
+use dit::models::{ListItem, StatusItem};
+use dit::report::{group_by_day, total_effort};
+use dit::utils::logging;
+use dit::utils::nice::Nice;
+use dit::utils::time::{now, start_of_day, Timestamp};
+
+use crate::commands::Dit;
+
+/// How many recent tasks are listed.
+const LIMIT: usize = 20;
+
+/// How often changes made by other processes, e.g. `dit` in another terminal, are picked up.
+const REFRESH_SECS: u64 = 5;
+
+const TICK_MILLIS: u64 = 250;
+
+const HELP: &str = "↑↓ select  ⏎/s switch  h halt  r resume  a append  c cancel  \
+                    v day/week  ←→ period  t today  PgUp/PgDn scroll  q quit";
+
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Day,
+    Week,
+}
+
+struct App<'a> {
+    dit: &'a Dit,
+    status: Vec<StatusItem>,
+    selected: TableState,
+    view: View,
+    /// Periods back from the current one.
+    offset: i64,
+    entries: Vec<ListItem>,
+    scroll: u16,
+    message: String,
+    /// Whether `c` was pressed, and `y` is awaited before the running entry is deleted.
+    canceling: bool,
+    refreshed: Instant,
+}
+
+pub fn run(dit: &Dit) -> Result<()> {
+    let mut terminal = start().context("Could not set up the terminal")?;
+    logging::capture(true);
+
+    // Otherwise the message would go to the alternate screen, left in raw mode
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = stop();
+        hook(info);
+    }));
+
+    let r = App::new(dit).and_then(|mut a| a.run(&mut terminal));
+
+    let _ = panic::take_hook();
+    logging::capture(false);
+    stop().context("Could not restore the terminal")?;
+    r
+}
+
+fn start() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    terminal.hide_cursor()?;
+    Ok(terminal)
+}
+
+fn stop() -> Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, Show)?;
+    Ok(())
+}
+
+impl<'a> App<'a> {
+    fn new(dit: &'a Dit) -> Result<Self> {
+        let mut app = App {
+            dit,
+            status: Vec::new(),
+            selected: TableState::default(),
+            view: View::Day,
+            offset: 0,
+            entries: Vec::new(),
+            scroll: 0,
+            message: String::new(),
+            canceling: false,
+            refreshed: Instant::now(),
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        loop {
+            terminal.draw(|f| self.draw(f))?;
+
+            if event::poll(std::time::Duration::from_millis(TICK_MILLIS))? {
+                if let Event::Key(key) = event::read()? {
+                    if !self.on_key(key)? {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if self.refreshed.elapsed().as_secs() >= REFRESH_SECS {
+                self.dit.repo.refresh()?;
+                self.reload()?;
+            }
+        }
+    }
+
+    /// Handles a key press, returning whether to go on.
+    fn on_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let now = now();
+
+        // Any other key answers no
+        let canceling = mem::take(&mut self.canceling);
+        if canceling && key.code != KeyCode::Char('y') {
+            self.message = "Not canceled".to_string();
+            return Ok(true);
+        }
+
+        let r = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(false)
+            }
+            KeyCode::Up | KeyCode::Char('k') => return Ok(self.select(-1)),
+            KeyCode::Down | KeyCode::Char('j') => return Ok(self.select(1)),
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(10);
+                return Ok(true);
+            }
+            KeyCode::PageDown => {
+                self.scroll = self.scroll.saturating_add(10);
+                return Ok(true);
+            }
+            KeyCode::Enter | KeyCode::Char('s') => self.switch(now),
+            KeyCode::Char('h') => self.halt(now),
+            KeyCode::Char('r') => self.dit.do_work_on_by_index(now, 0),
+            KeyCode::Char('a') => self.dit.do_append(),
+            KeyCode::Char('c') => return Ok(self.ask_cancel()),
+            KeyCode::Char('y') if canceling => self.dit.do_cancel(),
+            KeyCode::Char('v') => {
+                self.view = match self.view {
+                    View::Day => View::Week,
+                    View::Week => View::Day,
+                };
+                self.go_to(0);
+                Ok(())
+            }
+            KeyCode::Left => {
+                self.go_to(self.offset + 1);
+                Ok(())
+            }
+            KeyCode::Right => {
+                self.go_to((self.offset - 1).max(0));
+                Ok(())
+            }
+            KeyCode::Char('t') => {
+                self.go_to(0);
+                Ok(())
+            }
+            _ => return Ok(true),
+        };
+
+        let mut messages = logging::take_captured();
+        if let Err(e) = r {
+            messages.push(format!("Error: {:#}", e));
+        }
+        if !messages.is_empty() {
+            self.message = messages.join("; ");
+        }
+
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Like `switch-to`, but also works when no task is active.
+    fn switch(&self, now: Timestamp) -> Result<()> {
+        let id = match self.selected.selected().and_then(|i| self.status.get(i)) {
+            Some(s) => s.id.clone(),
+            None => return Ok(()),
+        };
+
+        if let Some(active) = self.dit.repo.is_clocked_in() {
+            if active == id {
+                return Ok(());
+            }
+            self.halt(now)?;
+        }
+        self.dit.do_work_on(&id, now)
+    }
+
+    /// Like `halt`, pointing at `fix-open` for entries too long to end now, as there is no
+    /// `--at` or `--force` here.
+    fn halt(&self, now: Timestamp) -> Result<()> {
+        if let Some(entry) = self.dit.too_long_entry(now) {
+            bail!(
+                "The entry started {} is longer than the maximum of {}; end it with `dit fix-open`",
+                entry.start.nice(),
+                self.dit.config.schedule.max_entry.nice()
+            );
+        }
+        self.dit.do_halt(now, false)
+    }
+
+    /// Asks before `cancel`, as it deletes the running entry.
+    fn ask_cancel(&mut self) -> bool {
+        match self.dit.repo.is_clocked_in() {
+            Some(id) => {
+                self.message = format!("Cancel the running entry of {}, deleting it? y/n", id);
+                self.canceling = true;
+            }
+            None => self.message = "Not working on any task".to_string(),
+        }
+        true
+    }
+
+    fn go_to(&mut self, offset: i64) {
+        self.offset = offset;
+        self.scroll = 0;
+    }
+
+    fn select(&mut self, delta: i64) -> bool {
+        if !self.status.is_empty() {
+            let i = self.selected.selected().unwrap_or(0) as i64 + delta;
+            let i = i.max(0).min(self.status.len() as i64 - 1);
+            self.selected.select(Some(i as usize));
+        }
+        true
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let id = self
+            .selected
+            .selected()
+            .and_then(|i| self.status.get(i))
+            .map(|s| s.id.clone());
+
+        self.status = self.dit.repo.get_status(LIMIT);
+
+        // Keep the selection on the same task, even if it moved
+        let i = id
+            .and_then(|id| self.status.iter().position(|s| s.id == id))
+            .unwrap_or(0);
+        self.selected.select(if self.status.is_empty() {
+            None
+        } else {
+            Some(i)
+        });
+
+        let (first, last) = self.period();
+        self.entries = self.dit.repo.get_listing(
+            Some(start_of_day(first)),
+            Some(start_of_day(last) - Duration::seconds(1)),
+        )?;
+
+        self.refreshed = Instant::now();
+        Ok(())
+    }
+
+    /// The first day of the period, and the day after it.
+    fn period(&self) -> (NaiveDate, NaiveDate) {
+        let today = Local::now().naive_local().date();
+
+        match self.view {
+            View::Day => {
+                let d = today - Duration::days(self.offset);
+                (d, d + Duration::days(1))
+            }
+            View::Week => {
+                let monday = today
+                    - Duration::days(i64::from(today.weekday().num_days_from_monday()))
+                    - Duration::weeks(self.offset);
+                (monday, monday + Duration::weeks(1))
+            }
+        }
+    }
+
+    fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Percentage(40),
+                Constraint::Min(5),
+                Constraint::Length(2),
+            ])
+            .split(f.size());
+
+        self.draw_active(f, chunks[0]);
+        self.draw_recent(f, chunks[1]);
+        self.draw_log(f, chunks[2]);
+
+        let footer = Paragraph::new(vec![
+            Spans::from(Span::raw(self.message.as_str())),
+            Spans::from(Span::styled(HELP, Style::default().fg(Color::DarkGray))),
+        ]);
+        f.render_widget(footer, chunks[3]);
+    }
+
+    fn draw_active<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let line = match self.status.first().filter(|s| s.log_entry.is_open()) {
+            Some(s) => Spans::from(vec![
+                Span::styled(
+                    format!("{} ", clock(s.effort())),
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(s.id.as_str(), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!(" {}  (total {})", s.title, s.total_effort.nice())),
+            ]),
+            None => Spans::from(Span::styled(
+                "Not working on any task",
+                Style::default().fg(Color::DarkGray),
+            )),
+        };
+
+        let p = Paragraph::new(line).block(Block::default().borders(Borders::ALL).title("Active"));
+        f.render_widget(p, area);
+    }
+
+    fn draw_recent<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect) {
+        let header = Row::new(vec!["Id", "Title", "Start", "Effort", "TotalEffort"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows = self.status.iter().map(|s| {
+            let style = match s.log_entry.is_open() {
+                true => Style::default().fg(Color::Green),
+                false => Style::default(),
+            };
+            Row::new(vec![
+                Cell::from(s.id.clone()),
+                Cell::from(s.title.clone()),
+                Cell::from(s.start().nice()),
+                Cell::from(s.effort().nice()),
+                Cell::from(s.total_effort.nice()),
+            ])
+            .style(style)
+        });
+
+        let widths = [
+            Constraint::Percentage(25),
+            Constraint::Percentage(30),
+            Constraint::Length(25),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ];
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title("Recent"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .widths(&widths);
+
+        f.render_stateful_widget(table, area, &mut self.selected);
+    }
+
+    fn draw_log<B: Backend>(&self, f: &mut Frame<B>, area: Rect) {
+        let (first, last) = self.period();
+        let title = match self.view {
+            View::Day => format!("Day {}", first.format("%a %F")),
+            View::Week => {
+                let w = first.iso_week();
+                format!(
+                    "Week {}-W{:02} ({} – {})",
+                    w.year(),
+                    w.week(),
+                    first.format("%F"),
+                    (last - Duration::days(1)).format("%F")
+                )
+            }
+        };
+        let title = format!("{}: {}", title, total_effort(&self.entries).nice());
+
+        let mut lines = Vec::new();
+        for (day, items) in group_by_day(&self.entries) {
+            if self.view == View::Week {
+                lines.push(Spans::from(Span::styled(
+                    format!("{}  {}", day.format("%a %F"), total_effort(items).nice()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )));
+            }
+            for x in items {
+                let end = match x.end() {
+                    Some(e) => e.format("%H:%M").to_string(),
+                    None => "…".to_string(),
+                };
+                lines.push(Spans::from(vec![
+                    Span::raw(format!("  {}–{:<5} ", x.start().format("%H:%M"), end)),
+                    Span::raw(format!("{:>10}  ", x.effort().nice())),
+                    Span::styled(x.id.as_str(), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(" {}", x.title)),
+                ]));
+            }
+        }
+        if lines.is_empty() {
+            lines.push(Spans::from(Span::styled(
+                "Nothing tracked",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+
+        let p = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((self.scroll, 0));
+        f.render_widget(p, area);
+    }
+}
+
+/// Formats a running duration like a stopwatch, e.g. `1:05:09`.
+fn clock(d: Duration) -> String {
+    let s = d.num_seconds().max(0);
+    format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+}
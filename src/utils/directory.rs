@@ -2,6 +2,7 @@ use anyhow::{bail, Context, Result};
 use dirs::home_dir;
 use log::trace;
 use std::env;
+use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -35,6 +36,11 @@ pub fn ensure_exists(path: &Path) -> Result<()> {
         .with_context(|| format!("Could not create directory: {}", path.display()))
 }
 
+/// Whether a file or directory is hidden, e.g. the index or a `.git` directory.
+pub fn is_hidden(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
 fn search_from(path: PathBuf) -> Result<PathBuf> {
     let mut ancerstors = path.ancestors();
     while let Some(p) = ancerstors.next() {
@@ -1,5 +1,5 @@
-use anyhow::{Context, Result};
-use dialoguer::Input;
+use anyhow::{bail, Context, Result};
+use dialoguer::{console, Confirm, FuzzySelect, Input};
 
 pub fn prompt(p: &str) -> Result<String> {
     Input::new()
@@ -16,3 +16,26 @@ pub fn prompt(p: &str) -> Result<String> {
         .map(|x| x.trim().to_string())
         .context("Could not read task title")
 }
+
+pub fn confirm(p: &str) -> Result<bool> {
+    Confirm::new()
+        .with_prompt(p)
+        .default(false)
+        .interact()
+        .context("Could not read confirmation")
+}
+
+/// Lets the user narrow down the items by typing, returning the index of the chosen one, or
+/// `None` if the prompt was left with Escape.
+pub fn pick(p: &str, items: &[String]) -> Result<Option<usize>> {
+    if !console::user_attended_stderr() {
+        bail!("Not running in a terminal; cannot prompt for: {}", p);
+    }
+
+    FuzzySelect::new()
+        .with_prompt(p)
+        .items(items)
+        .default(0)
+        .interact_opt()
+        .context("Could not read selection")
+}
@@ -1,4 +1,10 @@
+use lazy_static::lazy_static;
 use log::{Level, LevelFilter, Metadata, Record};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref CAPTURED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+}
 
 struct Logger;
 
@@ -8,6 +14,15 @@ impl log::Log for Logger {
     }
 
     fn log(&self, record: &Record) {
+        if let Some(captured) = CAPTURED.lock().unwrap().as_mut() {
+            match record.level() {
+                Level::Error => captured.push(format!("Error: {}", record.args())),
+                Level::Warn | Level::Info => captured.push(record.args().to_string()),
+                _ => {}
+            }
+            return;
+        }
+
         if record.level() == Level::Error {
             eprintln!("Error: {}", record.args())
         } else {
@@ -27,3 +42,21 @@ pub fn init(verbosity: u64) {
         _ => log::set_max_level(LevelFilter::Trace),
     }
 }
+
+/// While capturing, messages are kept instead of printed, so they do not garble a full-screen UI.
+pub fn capture(enabled: bool) {
+    *CAPTURED.lock().unwrap() = match enabled {
+        true => Some(Vec::new()),
+        false => None,
+    };
+}
+
+/// Returns the messages captured since the last call.
+pub fn take_captured() -> Vec<String> {
+    CAPTURED
+        .lock()
+        .unwrap()
+        .as_mut()
+        .map(std::mem::take)
+        .unwrap_or_default()
+}
@@ -2,5 +2,9 @@ pub mod directory;
 pub mod input;
 pub mod logging;
 pub mod nice;
+pub mod output;
 pub mod tables;
+pub mod template;
+#[cfg(test)]
+pub mod testing;
 pub mod time;
@@ -0,0 +1,109 @@
+//! Settles how listings are printed: colours, the terminal width and paging. Both colours and the
+//! width are looked up once, before stdout may be handed over to a pager.
+
+use anyhow::{Context, Result};
+use dialoguer::console::{self, Term};
+use log::debug;
+use std::env;
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const DEFAULT_PAGER: &str = "less";
+
+/// Quits when the output fits on one screen, passes colours through, and leaves the output on
+/// the screen; the same defaults as git.
+const DEFAULT_LESS: &str = "FRX";
+
+/// Columns of the terminal stdout is connected to, or zero if it is not one.
+static WIDTH: AtomicUsize = AtomicUsize::new(0);
+
+pub fn init() {
+    // https://no-color.org
+    let no_color = match env::var_os("NO_COLOR") {
+        Some(x) => !x.is_empty(),
+        None => false,
+    };
+    console::set_colors_enabled(console::colors_enabled() && !no_color);
+
+    let width = Term::stdout().size_checked().map(|(_, w)| w as usize);
+    WIDTH.store(width.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// The width of the terminal the output goes to, if it goes to one.
+pub fn width() -> Option<usize> {
+    match WIDTH.load(Ordering::Relaxed) {
+        0 => None,
+        w => Some(w),
+    }
+}
+
+/// Sends stdout through `$PAGER` until dropped, when stdout is a terminal and a pager is set.
+pub struct Pager {
+    child: Child,
+    #[cfg(unix)]
+    stdout: libc::c_int,
+}
+
+impl Pager {
+    pub fn start() -> Result<Option<Pager>> {
+        if !Term::stdout().is_term() {
+            return Ok(None);
+        }
+        let command = env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+        if command.trim().is_empty() || command.trim() == "cat" {
+            return Ok(None);
+        }
+        Pager::spawn(&command)
+    }
+
+    #[cfg(unix)]
+    fn spawn(command: &str) -> Result<Option<Pager>> {
+        use std::os::unix::io::AsRawFd;
+
+        debug!("Paging with: {}", command);
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env(
+                "LESS",
+                env::var("LESS").unwrap_or_else(|_| DEFAULT_LESS.to_string()),
+            )
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Could not start pager: {}", command))?;
+
+        let pipe = child.stdin.take().unwrap();
+        // The pager reads what is written to stdout from now on; the terminal is restored on drop.
+        // Quitting the pager early ends dit quietly, as with any other command in a pipeline.
+        let stdout = unsafe {
+            let saved = libc::dup(libc::STDOUT_FILENO);
+            libc::dup2(pipe.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+            saved
+        };
+
+        Ok(Some(Pager { child, stdout }))
+    }
+
+    #[cfg(not(unix))]
+    fn spawn(_: &str) -> Result<Option<Pager>> {
+        Ok(None)
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+
+        // Closing the pipe lets the pager see the end of the output
+        #[cfg(unix)]
+        unsafe {
+            libc::dup2(self.stdout, libc::STDOUT_FILENO);
+            libc::close(self.stdout);
+        }
+
+        let _ = self.child.wait();
+    }
+}
@@ -1,46 +1,174 @@
+use anyhow::Result;
+use dialoguer::console::{measure_text_width, Style};
+use std::io;
+
+use crate::formats::html;
+use crate::utils::output;
+
+/// Wrapped cells are cut after this many lines.
+const MAX_LINES: usize = 3;
+
+/// Wrapping columns are not narrowed below this, even if the table then overflows.
+const MIN_WRAP_WIDTH: usize = 10;
+
 pub struct Table<R> {
     columns: Vec<Column<R>>,
+    emphasis: Option<Emphasize<R>>,
 }
 
+type Emphasize<R> = Box<dyn Fn(&R) -> Emphasis>;
+
 pub struct Column<R> {
     select: Box<dyn Fn(&R) -> String>,
     name: String,
+    align: Align,
+    wrap: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+}
+
+/// How a row stands out, when colours are enabled.
+pub enum Emphasis {
+    Normal,
+    /// E.g. the entry being clocked.
+    Active,
+    /// E.g. a task over its estimate.
+    Warning,
 }
 
 impl<R> Table<R> {
     pub fn new(columns: Vec<Column<R>>) -> Self {
-        Table { columns }
+        Table {
+            columns,
+            emphasis: None,
+        }
+    }
+
+    pub fn emphasis<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&R) -> Emphasis + 'static,
+    {
+        self.emphasis = Some(Box::new(f));
+        self
     }
 
     pub fn print(&self, data: &[R]) {
         let s_data = self.select(data);
 
-        let widths = self.calc_widths(&s_data);
+        let mut widths = self.calc_widths(&s_data);
+        if let Some(w) = output::width() {
+            self.fit(&mut widths, w);
+        }
 
+        let header = Style::new().bold();
         let c_names: Vec<String> = self
             .columns
             .iter()
-            .map(|c| &c.name)
             .zip(&widths)
-            .map(|(r, w)| format!("{:<width$}", r, width = w))
+            .map(|(c, w)| header.apply_to(pad(&c.name, *w, c.align)).to_string())
             .collect();
         println!("| {} |", c_names.join(" | "));
 
-        let seps: Vec<String> = widths.iter()
+        let seps: Vec<String> = widths
+            .iter()
             .map(|w| format!("{:-<width$}", "", width = w + 2))
             .collect();
         println!("|{}|", seps.join("+"));
 
-        for r in s_data.iter() {
-            let row: Vec<String> = r
+        for (x, r) in data.iter().zip(&s_data) {
+            let style = match self.emphasis.as_ref().map(|f| f(x)) {
+                Some(Emphasis::Active) => Style::new().green(),
+                Some(Emphasis::Warning) => Style::new().red(),
+                _ => Style::new(),
+            };
+
+            let cells: Vec<Vec<String>> = r
                 .iter()
+                .zip(&self.columns)
                 .zip(&widths)
-                .map(|(r, w)| format!("{:<width$}", r, width = w))
+                .map(|((s, c), w)| match c.wrap {
+                    true => wrap(s, *w),
+                    false => vec![s.clone()],
+                })
                 .collect();
+            let lines = cells.iter().map(Vec::len).max().unwrap_or(1);
+
+            for i in 0..lines {
+                let row: Vec<String> = cells
+                    .iter()
+                    .zip(&self.columns)
+                    .zip(&widths)
+                    .map(|((s, c), w)| {
+                        let s = s.get(i).map(String::as_str).unwrap_or_default();
+                        style.apply_to(pad(s, *w, c.align)).to_string()
+                    })
+                    .collect();
+                println!("| {} |", row.join(" | "));
+            }
+        }
+    }
+
+    pub fn print_csv(&self, data: &[R]) -> Result<()> {
+        let mut w = csv::Writer::from_writer(io::stdout());
+
+        w.write_record(self.columns.iter().map(|c| &c.name))?;
+        for r in self.select(data) {
+            w.write_record(&r)?;
+        }
+        w.flush()?;
+
+        Ok(())
+    }
+
+    /// Prints a GitHub-flavoured Markdown table.
+    pub fn print_markdown(&self, data: &[R]) {
+        let escape = |s: &str| s.replace('|', "\\|");
+
+        let c_names: Vec<String> = self.columns.iter().map(|c| escape(&c.name)).collect();
+        println!("| {} |", c_names.join(" | "));
+
+        let seps: Vec<&str> = self
+            .columns
+            .iter()
+            .map(|c| match c.align {
+                Align::Left => "---",
+                Align::Right => "--:",
+            })
+            .collect();
+        println!("|{}|", seps.join("|"));
+
+        for r in self.select(data) {
+            let row: Vec<String> = r.iter().map(|x| escape(x)).collect();
             println!("| {} |", row.join(" | "));
         }
     }
 
+    pub fn html(&self, data: &[R]) -> String {
+        let class = |c: &Column<R>| match c.align {
+            Align::Left => "",
+            Align::Right => " class=\"num\"",
+        };
+
+        let mut s = String::from("<table>\n<thead><tr>");
+        for c in &self.columns {
+            s.push_str(&format!("<th{}>{}</th>", class(c), html::escape(&c.name)));
+        }
+        s.push_str("</tr></thead>\n<tbody>\n");
+        for r in self.select(data) {
+            s.push_str("<tr>");
+            for (x, c) in r.iter().zip(&self.columns) {
+                s.push_str(&format!("<td{}>{}</td>", class(c), html::escape(x)));
+            }
+            s.push_str("</tr>\n");
+        }
+        s.push_str("</tbody>\n</table>\n");
+        s
+    }
+
     fn select(&self, data: &[R]) -> Vec<Vec<String>> {
         data.iter()
             .map(|x| self.columns.iter().map(|c| c.select(x)).collect())
@@ -48,7 +176,11 @@ impl<R> Table<R> {
     }
 
     fn calc_widths(&self, s_data: &Vec<Vec<String>>) -> Vec<usize> {
-        let w: Vec<usize> = self.columns.iter().map(|c| c.name.len()).collect();
+        let w: Vec<usize> = self
+            .columns
+            .iter()
+            .map(|c| measure_text_width(&c.name))
+            .collect();
 
         self.columns
             .iter()
@@ -56,10 +188,31 @@ impl<R> Table<R> {
             .map(|(i, _)| {
                 s_data
                     .iter()
-                    .fold(w[i], |a, x| std::cmp::max(x[i].len(), a))
+                    .fold(w[i], |a, x| std::cmp::max(measure_text_width(&x[i]), a))
             })
             .collect()
     }
+
+    /// Narrows the widest wrapping column, one character at a time, until the table fits.
+    fn fit(&self, widths: &mut Vec<usize>, available: usize) {
+        let total = |widths: &Vec<usize>| widths.iter().sum::<usize>() + 3 * widths.len() + 1;
+
+        while total(widths) > available {
+            let widest = self
+                .columns
+                .iter()
+                .zip(widths.iter())
+                .enumerate()
+                .filter(|(_, (c, w))| c.wrap && **w > MIN_WRAP_WIDTH)
+                .max_by_key(|(_, (_, w))| **w)
+                .map(|(i, _)| i);
+
+            match widest {
+                Some(i) => widths[i] -= 1,
+                None => break,
+            }
+        }
+    }
 }
 
 impl<R> Column<R> {
@@ -70,18 +223,86 @@ impl<R> Column<R> {
         Column {
             select: Box::new(f),
             name: name.to_string(),
+            align: Align::Left,
+            wrap: false,
         }
     }
 
+    /// The default; spelled out in lists of columns.
+    pub fn left(mut self) -> Self {
+        self.align = Align::Left;
+        self
+    }
+
+    /// For numbers and durations.
+    pub fn right(mut self) -> Self {
+        self.align = Align::Right;
+        self
+    }
+
+    /// Lets the column be narrowed to fit the terminal, wrapping its cells.
+    pub fn wrap(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
     pub fn select(&self, x: &R) -> String {
         (self.select)(x)
     }
 }
 
+fn pad(s: &str, width: usize, align: Align) -> String {
+    let fill = " ".repeat(width.saturating_sub(measure_text_width(s)));
+    match align {
+        Align::Left => format!("{}{}", s, fill),
+        Align::Right => format!("{}{}", fill, s),
+    }
+}
+
+/// Breaks text into lines of at most `width` characters, at spaces where possible. Text that
+/// would take more than `MAX_LINES` lines is cut, ending with an ellipsis.
+fn wrap(s: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in s.split(' ') {
+        let mut word: Vec<char> = word.chars().collect();
+        loop {
+            let used = line.chars().count();
+            let gap = if used == 0 { 0 } else { 1 };
+
+            if used + gap + word.len() <= width {
+                if gap == 1 {
+                    line.push(' ');
+                }
+                line.extend(word);
+                break;
+            }
+            if used > 0 {
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            // Words longer than a whole line are split
+            let rest = word.split_off(width.max(1));
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+    }
+    lines.push(line);
+
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        let last: Vec<char> = lines[MAX_LINES - 1].chars().collect();
+        let kept = last.len().min(width.saturating_sub(1));
+        lines[MAX_LINES - 1] = last[..kept].iter().chain(Some(&'…')).collect();
+    }
+    lines
+}
+
 #[cfg(test)]
 mod test {
 
-    use super::{Column, Table};
+    use super::{wrap, Column, Table};
 
     struct Foo<'a> {
         a: i32,
@@ -91,8 +312,8 @@ mod test {
     #[test]
     fn test_table() {
         let table = Table::new(vec![
-            Column::<Foo>::new("Foo", |x| x.a.to_string()),
-            Column::<Foo>::new("B", |x| x.b.to_string()),
+            Column::<Foo>::new("Foo", |x| x.a.to_string()).right(),
+            Column::<Foo>::new("B", |x| x.b.to_string()).wrap(),
         ]);
 
         let data = vec![
@@ -102,5 +323,25 @@ mod test {
         ];
 
         table.print(&data);
+
+        let mut widths = vec![3, 30];
+        table.fit(&mut widths, 30);
+        assert_eq!(widths, vec![3, 20]);
+        table.fit(&mut widths, 10);
+        assert_eq!(widths, vec![3, 10]);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("", 10), vec![""]);
+        assert_eq!(
+            wrap("Review the pull request", 10),
+            vec!["Review the", "pull", "request"]
+        );
+        assert_eq!(wrap("abcdefghijklmn", 6), vec!["abcdef", "ghijkl", "mn"]);
+        assert_eq!(
+            wrap("one two three four five six", 5),
+            vec!["one", "two", "thre…"]
+        );
     }
 }
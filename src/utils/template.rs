@@ -0,0 +1,285 @@
+//! A small template language for one-line output, e.g. `{id} {effort:hm}`.
+//!
+//! Placeholders are `{field}` or `{field:spec}`, optionally followed by filters, e.g.
+//! `{title|trunc:20|pad:20}`; `{{` and `}}` stand for literal braces. The spec of a timestamp is a
+//! `strftime` format; the one of a duration is one of the styles in `format_duration`. Filters
+//! apply in order to the formatted value:
+//!
+//! - `trunc:N` cuts it to N characters, ending with an ellipsis
+//! - `pad:N` pads it with spaces to N characters, on the right; `lpad:N` does so on the left
+//! - `or:TEXT` replaces it with TEXT when empty
+
+use anyhow::{anyhow, bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::Duration;
+
+use crate::utils::nice::Nice;
+use crate::utils::time::Timestamp;
+
+pub enum Value {
+    Text(String),
+    Time(Timestamp),
+    Duration(Duration),
+    Empty,
+}
+
+impl From<String> for Value {
+    fn from(x: String) -> Self {
+        Value::Text(x)
+    }
+}
+
+impl From<Timestamp> for Value {
+    fn from(x: Timestamp) -> Self {
+        Value::Time(x)
+    }
+}
+
+impl From<Duration> for Value {
+    fn from(x: Duration) -> Self {
+        Value::Duration(x)
+    }
+}
+
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(x: Option<T>) -> Self {
+        x.map(Into::into).unwrap_or(Value::Empty)
+    }
+}
+
+enum Piece {
+    Literal(String),
+    Field {
+        name: String,
+        spec: Option<String>,
+        filters: Vec<Filter>,
+    },
+}
+
+enum Filter {
+    Trunc(usize),
+    Pad(usize),
+    LPad(usize),
+    Or(String),
+}
+
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    /// Parses a template whose placeholders may only name the given fields.
+    pub fn parse(s: &str, fields: &[&str]) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => bail!("Unclosed placeholder in template: {}", s),
+                        }
+                    }
+
+                    let mut parts = placeholder.split('|');
+                    let field = parts.next().unwrap_or_default();
+                    let filters = parts.map(parse_filter).collect::<Result<Vec<_>>>()?;
+
+                    let (name, spec) = match field.find(':') {
+                        Some(i) => (&field[..i], Some(field[i + 1..].to_string())),
+                        None => (field, None),
+                    };
+                    if !fields.contains(&name) {
+                        bail!(
+                            "Unknown field in template: '{}'; expected one of: {}",
+                            name,
+                            fields.join(", ")
+                        );
+                    }
+
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(literal.split_off(0)));
+                    }
+                    pieces.push(Piece::Field {
+                        name: name.to_string(),
+                        spec,
+                        filters,
+                    });
+                }
+                '}' => bail!("Unmatched '}}' in template: {}", s),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Template { pieces })
+    }
+
+    /// Renders the template, looking up the value of each placeholder with `value`.
+    pub fn render<F>(&self, value: F) -> Result<String>
+    where
+        F: Fn(&str) -> Value,
+    {
+        let mut r = String::new();
+
+        for p in &self.pieces {
+            match p {
+                Piece::Literal(s) => r.push_str(s),
+                Piece::Field {
+                    name,
+                    spec,
+                    filters,
+                } => {
+                    let x = format_value(&value(name), spec.as_deref())?;
+                    r.push_str(&filters.iter().fold(x, apply_filter));
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+fn parse_filter(s: &str) -> Result<Filter> {
+    let (name, arg) = match s.find(':') {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, ""),
+    };
+    let width = || {
+        arg.parse::<usize>().map_err(|_| {
+            anyhow!(
+                "Filter '{}' needs a number of characters, e.g. {}:20",
+                name,
+                name
+            )
+        })
+    };
+
+    Ok(match name {
+        "trunc" => Filter::Trunc(width()?),
+        "pad" => Filter::Pad(width()?),
+        "lpad" => Filter::LPad(width()?),
+        "or" => Filter::Or(arg.to_string()),
+        _ => bail!(
+            "Unknown filter in template: '{}'; expected one of: trunc, pad, lpad, or",
+            name
+        ),
+    })
+}
+
+fn apply_filter(x: String, filter: &Filter) -> String {
+    match filter {
+        Filter::Trunc(n) if x.chars().count() > *n => match n {
+            0 => String::new(),
+            _ => x.chars().take(n - 1).chain(Some('…')).collect(),
+        },
+        Filter::Trunc(_) => x,
+        Filter::Pad(n) => format!("{:<width$}", x, width = n),
+        Filter::LPad(n) => format!("{:>width$}", x, width = n),
+        Filter::Or(y) if x.is_empty() => y.clone(),
+        Filter::Or(_) => x,
+    }
+}
+
+fn format_value(value: &Value, spec: Option<&str>) -> Result<String> {
+    Ok(match (value, spec) {
+        (Value::Empty, _) => String::new(),
+        (Value::Text(x), None) => x.clone(),
+        (Value::Text(_), Some(s)) => bail!("Text fields take no format: {}", s),
+        (Value::Time(x), None) => x.nice(),
+        (Value::Time(x), Some(s)) => {
+            if StrftimeItems::new(s).any(|i| i == Item::Error) {
+                bail!("Invalid date/time format: {}", s);
+            }
+            x.format(s).to_string()
+        }
+        (Value::Duration(x), None) => x.nice(),
+        (Value::Duration(x), Some(s)) => format_duration(x, s)?,
+    })
+}
+
+/// Formats a duration in one of the styles: `hm` (`1h05m`), `h:mm` (`1:05`), `min` (`65`) or `h`
+/// (`1.08`).
+fn format_duration(x: &Duration, style: &str) -> Result<String> {
+    let sign = if *x < Duration::zero() { "-" } else { "" };
+    let minutes = x.num_minutes().abs();
+
+    Ok(match style {
+        "hm" if minutes < 60 => format!("{}{}m", sign, minutes),
+        "hm" => format!("{}{}h{:02}m", sign, minutes / 60, minutes % 60),
+        "h:mm" => format!("{}{}:{:02}", sign, minutes / 60, minutes % 60),
+        "min" => format!("{}{}", sign, minutes),
+        "h" => format!("{:.2}", x.num_seconds() as f64 / 3600.0),
+        _ => bail!(
+            "Invalid duration style: {}; expected one of: hm, h:mm, min, h",
+            style
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Template, Value};
+    use crate::utils::time::parse_timestamp;
+    use chrono::Duration;
+
+    #[test]
+    fn test_render() {
+        let start = parse_timestamp("2021-03-04-09:05").unwrap();
+        let value = |name: &str| match name {
+            "id" => Value::from("foo/bar".to_string()),
+            "start" => Value::from(start),
+            "effort" => Value::from(Duration::minutes(65)),
+            _ => Value::Empty,
+        };
+        let fields = ["id", "start", "effort", "estimate"];
+        let render = |s| Template::parse(s, &fields).unwrap().render(value).unwrap();
+
+        assert_eq!(render("{id} {effort:hm}"), "foo/bar 1h05m");
+        assert_eq!(render("{start:%H:%M} {effort:h:mm}"), "09:05 1:05");
+        assert_eq!(render("{effort:min}min {effort:h}h"), "65min 1.08h");
+        assert_eq!(render("{effort}"), "1h5min");
+        assert_eq!(render("[{estimate}] {{id}}"), "[] {id}");
+        assert_eq!(
+            render("{id|trunc:5}|{id|pad:9}|{id|lpad:9}|"),
+            "foo/…|foo/bar  |  foo/bar|"
+        );
+        assert_eq!(
+            render("{id|trunc:20} {estimate|or:-|lpad:3}"),
+            "foo/bar   -"
+        );
+        assert_eq!(render("{start:%H:%M|pad:6}."), "09:05 .");
+
+        assert!(Template::parse("{title}", &fields).is_err());
+        assert!(Template::parse("{id", &fields).is_err());
+        assert!(Template::parse("id}", &fields).is_err());
+        assert!(Template::parse("{id|trunc}", &fields).is_err());
+        assert!(Template::parse("{id|upper}", &fields).is_err());
+        assert!(Template::parse("{effort:hours}", &fields)
+            .unwrap()
+            .render(value)
+            .is_err());
+        assert!(Template::parse("{id:%H}", &fields)
+            .unwrap()
+            .render(value)
+            .is_err());
+    }
+}
@@ -0,0 +1,29 @@
+//! Fixtures shared by the tests of the library and of the binary, which includes this file too.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A fresh directory under the system's temporary one, removed again when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Named after the test and the process, so that tests running side by side do not collide.
+    pub fn new(name: &str) -> Self {
+        let p = env::temp_dir().join(format!("dit-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&p);
+        fs::create_dir_all(&p).unwrap();
+        TempDir(p)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
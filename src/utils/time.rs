@@ -1,4 +1,4 @@
-use chrono::{Date, DateTime, Duration, FixedOffset, Local, TimeZone};
+use chrono::{Date, DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
 
@@ -26,6 +26,28 @@ pub fn now() -> Timestamp {
     local_to_fixed(Local::now())
 }
 
+/// Converts a UTC time into the local timezone.
+pub fn to_local(utc: DateTime<Utc>) -> Timestamp {
+    local_to_fixed(utc.with_timezone(&Local))
+}
+
+/// Midnight at the start of the given day, in the local timezone.
+pub fn start_of_day(day: NaiveDate) -> Timestamp {
+    at(day, NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+}
+
+/// The given time of day in the local timezone; times skipped by a change to summer time are
+/// taken as UTC.
+pub fn at(day: NaiveDate, time: NaiveTime) -> Timestamp {
+    let local = day.and_time(time);
+    local_to_fixed(
+        Local
+            .from_local_datetime(&local)
+            .earliest()
+            .unwrap_or_else(|| Local.from_utc_datetime(&local)),
+    )
+}
+
 fn local_to_fixed(local_date_time: DateTime<Local>) -> DateTime<FixedOffset> {
     local_date_time.with_timezone(local_date_time.offset())
 }
@@ -78,7 +100,17 @@ pub fn parse_timestamp(x: &str) -> Option<Timestamp> {
     try_timestamp(x).or(try_time(x)).or(try_duration(x))
 }
 
-fn parse_duration(x: &str) -> Option<Duration> {
+/// Parses `today`, `yesterday` or a `YYYY-MM-DD` date.
+pub fn parse_day(x: &str) -> Option<NaiveDate> {
+    let today = Local::now().naive_local().date();
+    match x {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => NaiveDate::parse_from_str(x, "%F").ok(),
+    }
+}
+
+pub fn parse_duration(x: &str) -> Option<Duration> {
     DURATION_RE.captures(x).map(|m| {
         let s = i(&m, "d") * 86400 + i(&m, "h") * 3600 + i(&m, "min") * 60 + i(&m, "s");
         Duration::seconds(i64::from(s))
@@ -135,8 +167,12 @@ pub mod timestamp {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        DateTime::parse_from_str(s.as_str(), TIMESTAMP_FORMAT)
-            .map_err(|e| D::Error::custom(format!("Invalid datetime: {}", e)))
+        from_str(s.as_str())
+    }
+
+    fn from_str<E: Error>(s: &str) -> Result<Timestamp, E> {
+        DateTime::parse_from_str(s, TIMESTAMP_FORMAT)
+            .map_err(|e| E::custom(format!("Invalid datetime: {}", e)))
     }
 
     pub fn serialize<S>(value: &Timestamp, serializer: S) -> Result<S::Ok, S::Error>
@@ -148,15 +184,19 @@ pub mod timestamp {
 
     pub mod optional {
 
-        use serde::{Deserializer, Serializer};
+        use serde::{Deserialize, Deserializer, Serializer};
 
         use super::super::Timestamp;
 
+        /// Also accepts an explicit null, as written by serializers that do not skip `None`.
         pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Timestamp>, D::Error>
         where
             D: Deserializer<'de>,
         {
-            super::deserialize(deserializer).map(|x| Some(x))
+            match Option::<String>::deserialize(deserializer)? {
+                Some(s) => super::from_str(s.as_str()).map(Some),
+                None => Ok(None),
+            }
         }
 
         pub fn serialize<S>(value: &Option<Timestamp>, serializer: S) -> Result<S::Ok, S::Error>
@@ -183,8 +223,11 @@ pub mod duration {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        parse_duration(s.as_str())
-            .ok_or_else(|| D::Error::custom(format!("Invalid duration: {}", s)))
+        from_str(s.as_str())
+    }
+
+    fn from_str<E: Error>(s: &str) -> Result<Duration, E> {
+        parse_duration(s).ok_or_else(|| E::custom(format!("Invalid duration: {}", s)))
     }
 
     pub fn serialize<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
@@ -193,12 +236,40 @@ pub mod duration {
     {
         serializer.serialize_str(value.nice().as_str())
     }
+
+    pub mod optional {
+
+        use chrono::Duration;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        /// Also accepts an explicit null, as written by serializers that do not skip `None`.
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match Option::<String>::deserialize(deserializer)? {
+                Some(s) => super::from_str(s.as_str()).map(Some),
+                None => Ok(None),
+            }
+        }
+
+        pub fn serialize<S>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match value {
+                Some(x) => super::serialize(x, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::parse_timestamp;
+    use super::{parse_day, parse_timestamp};
+    use chrono::{Local, NaiveDate};
 
     macro_rules! assert_parses {
         ($expr:expr) => {{
@@ -255,4 +326,18 @@ mod tests {
         assert_parses_not!("2020-10-20");
         assert_parses_not!("2020-10-20-11");
     }
+
+    #[test]
+    fn test_parse_day() {
+        let today = Local::now().naive_local().date();
+
+        assert_eq!(parse_day("today"), Some(today));
+        assert_eq!(parse_day("yesterday"), today.pred_opt());
+        assert_eq!(
+            parse_day("2020-10-20"),
+            NaiveDate::from_ymd_opt(2020, 10, 20)
+        );
+        assert_eq!(parse_day("2020-10-32"), None);
+        assert_eq!(parse_day("tomorrow"), None);
+    }
 }